@@ -0,0 +1,13 @@
+#!/bin/sh
+# Builds the scalar (pkg) and SIMD (pkg-simd) artifacts, index.html loads
+# whichever the browser supports. Extra arguments are passed to wasm-pack.
+set -e
+wasm-pack build --release --target web --out-dir pkg "$@"
+wasm-pack build --release --target web --out-dir pkg-simd "$@" -- \
+    --features simd128 \
+    --config 'target.wasm32-unknown-unknown.rustflags=["-Ctarget-feature=+simd128"]'
+
+# Tests run on the host (cargo test --target x86_64-unknown-linux-gnu), except
+# for the SIMD kernels, which compare against the scalar fallback on wasm32:
+#   cargo test --features simd128 \
+#       --config 'target.wasm32-unknown-unknown.rustflags=["-Ctarget-feature=+simd128"]' simd
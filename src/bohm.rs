@@ -0,0 +1,219 @@
+use nalgebra::SVector;
+use crate::wavefunc::Superposition;
+
+// Steps of integrate before it completes the interval in a single step
+const MAX_ITERATIONS: usize = 10000;
+
+/// Particle positions as separate x, y, z components
+pub type Pos<const D: usize> = [SVector<f32, D>; 3];
+
+// Runge-Kutta-Fehlberg 4(5) tableau
+const RKF_C: [f32; 6] = [0.0, 1.0/4.0, 3.0/8.0, 12.0/13.0, 1.0, 1.0/2.0];
+const RKF_A: [&[f32]; 6] = [
+    &[],
+    &[1.0/4.0],
+    &[3.0/32.0, 9.0/32.0],
+    &[1932.0/2197.0, -7200.0/2197.0, 7296.0/2197.0],
+    &[439.0/216.0, -8.0, 3680.0/513.0, -845.0/4104.0],
+    &[-8.0/27.0, 2.0, -3544.0/2565.0, 1859.0/4104.0, -11.0/40.0],
+];
+const RKF_B5: [f32; 6] = [16.0/135.0, 0.0, 6656.0/12825.0, 28561.0/56430.0, -9.0/50.0, 2.0/55.0];
+const RKF_B4: [f32; 6] = [25.0/216.0, 0.0, 1408.0/2565.0, 2197.0/4104.0, -1.0/5.0, 0.0];
+
+/// Guiding equation v = (ħ/μ) Im(∇ψ/ψ), in atomic units
+pub fn velocity<const D: usize>(
+    wavefunc: &Superposition,
+    p: &Pos<D>,
+    t: f32,
+) -> Pos<D> {
+    let (psi, grad) = wavefunc.eval_grad(&p[0], &p[1], &p[2], t);
+    let k = 1.0/wavefunc.reduced_mass();
+    grad.map(|d| d.zip_map(&psi, |d, v| (d/v).im*k))
+}
+
+fn offset<const D: usize>(
+    p: &Pos<D>,
+    ks: &[Pos<D>],
+    w: &[f32],
+    dt: f32,
+) -> Pos<D> {
+    let mut q = *p;
+    for (k, w) in ks.iter().zip(w) {
+        for j in 0..3 {
+            q[j] += k[j]*(w*dt);
+        }
+    }
+    q
+}
+
+/// Classic fourth order Runge-Kutta step of size dt
+pub fn rk4<const D: usize>(
+    wavefunc: &Superposition,
+    p: &mut Pos<D>,
+    t: f32,
+    dt: f32,
+) {
+    let k1 = velocity(wavefunc, p, t);
+    let k2 = velocity(wavefunc, &offset(p, &[k1], &[0.5], dt), t+0.5*dt);
+    let k3 = velocity(wavefunc, &offset(p, &[k2], &[0.5], dt), t+0.5*dt);
+    let k4 = velocity(wavefunc, &offset(p, &[k3], &[1.0], dt), t+dt);
+    *p = offset(p, &[k1, k2, k3, k4], &[1.0/6.0, 1.0/3.0, 1.0/3.0, 1.0/6.0], dt);
+}
+
+/// Adaptive Runge-Kutta-Fehlberg step, the largest error in the batch is kept below tol.
+/// Particles with an undefined error, at a node or on the z axis, are left out of the
+/// error and stay in place. Returns whether the step was accepted and the next step size.
+pub fn rk45<const D: usize>(
+    wavefunc: &Superposition,
+    p: &mut Pos<D>,
+    t: f32,
+    dt: f32,
+    tol: f32,
+) -> (bool, f32) {
+    let mut ks = Vec::<Pos<D>>::with_capacity(6);
+    for i in 0..6 {
+        let q = offset(p, &ks, RKF_A[i], dt);
+        ks.push(velocity(wavefunc, &q, t+RKF_C[i]*dt));
+    }
+    let p5 = offset(p, &ks, &RKF_B5, dt);
+    let p4 = offset(p, &ks, &RKF_B4, dt);
+    let errs: [f32; D] = std::array::from_fn(|i| {
+        (0..3).map(|j| (p5[j][i]-p4[j][i]).powi(2)).sum::<f32>().sqrt()
+    });
+    let err = errs.iter().filter(|e| e.is_finite()).fold(0.0, |a: f32, &e| a.max(e));
+    let accept = err <= tol;
+    if accept {
+        for i in (0..D).filter(|&i| errs[i].is_finite()) {
+            (0..3).for_each(|j| p[j][i] = p5[j][i]);
+        }
+    }
+    let factor = if err > 0.0 { 0.9*(tol/err).powf(0.2) } else { 5.0 };
+
+    (accept, dt*factor.clamp(0.2, 5.0))
+}
+
+/// Integrates from t0 to t1 with adaptive steps, starting with step size dt.
+/// Particles whose velocity is undefined stay in place without affecting the
+/// step size of the others. Where the step size collapses a fixed step is taken
+/// instead, and after MAX_ITERATIONS the rest is a single step.
+/// Returns the last step size, to be reused by the next call.
+pub fn integrate<const D: usize>(
+    wavefunc: &Superposition,
+    p: &mut Pos<D>,
+    t0: f32,
+    t1: f32,
+    mut dt: f32,
+    tol: f32,
+) -> f32 {
+    let h_min = f32::EPSILON*t1.abs().max(1.0);
+    let mut t = t0;
+    for i in 0.. {
+        if t >= t1 { break }
+        let h = if i < MAX_ITERATIONS { dt.min(t1-t) } else { t1-t };
+        let (accept, next) = rk45(wavefunc, p, t, h, tol);
+        let forced = !accept && (!next.is_finite() || next < h_min || i >= MAX_ITERATIONS);
+        if forced {
+            let mut q = *p;
+            rk4(wavefunc, &mut q, t, h);
+            for k in 0..D {
+                if (0..3).all(|j| q[j][k].is_finite()) {
+                    (0..3).for_each(|j| p[j][k] = q[j][k]);
+                }
+            }
+        }
+        if accept || forced {
+            t = if h == t1-t { t1 } else { t+h };
+        }
+        dt = if next.is_finite() { next.max(h_min) } else { h };
+    }
+    dt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Complex;
+    use crate::wavefunc::{Psi, QuantumNumbers, M_PROTON};
+
+    fn circular(n: u32) -> Superposition {
+        let qn = QuantumNumbers::new(n, n-1, n as i32-1).unwrap();
+        let psi = Psi::builder(qn).reduced_mass(1.0, M_PROTON).build();
+        Superposition::new(vec![(Complex::new(1.0, 0.0), psi)]).unwrap()
+    }
+
+    fn points(r: f32) -> Pos<4> {
+        [
+            SVector::from([r, 0.0, -0.5*r, 0.3*r]),
+            SVector::from([0.0, 0.7*r, 0.5*r, -0.4*r]),
+            SVector::from([0.2*r, -0.3*r, 0.0, 0.6*r]),
+        ]
+    }
+
+    /// |n, l = n-1, m = l⟩ circulates about the z axis at |v| = m/(μρ)
+    #[test]
+    fn circular_orbit_velocity() {
+        for n in 1..=6 {
+            let wavefunc = circular(n);
+            let (m, mu) = ((n-1) as f32, wavefunc.reduced_mass());
+            let p = points(wavefunc.length_scale()*(n*n) as f32);
+            let v = velocity(&wavefunc, &p, 0.7);
+            for i in 0..4 {
+                let (x, y) = (p[0][i], p[1][i]);
+                let rho_2 = x*x+y*y;
+                let expected = [-y*m/(mu*rho_2), x*m/(mu*rho_2), 0.0];
+                for j in 0..3 {
+                    let scale = m.max(1.0)/(mu*rho_2.sqrt());
+                    assert!((v[j][i]-expected[j]).abs() <= 1e-4*scale, "n={} v={:?}", n, v);
+                }
+            }
+        }
+    }
+
+    /// Adaptive integration follows the circle, and particles on the z axis, where
+    /// the velocity of m ≠ 0 is undefined, stay in place
+    #[test]
+    fn circular_orbit_integration() {
+        let wavefunc = circular(3);
+        let mu = wavefunc.reduced_mass();
+        let mut p = points(9.0*wavefunc.length_scale());
+        let mut axis: Pos<4> = [SVector::zeros(), SVector::zeros(), points(1.0)[2]];
+        let (start, axis_start) = (p, axis);
+        let t1 = 50.0;
+        integrate(&wavefunc, &mut p, 0.0, t1, 0.1, 1e-5);
+        integrate(&wavefunc, &mut axis, 0.0, t1, 0.1, 1e-5);
+        for i in 0..4 {
+            let (x, y) = (start[0][i], start[1][i]);
+            let rho_2 = x*x+y*y;
+            let angle = 2.0*t1/(mu*rho_2);
+            let (s, c) = angle.sin_cos();
+            let expected = [c*x-s*y, s*x+c*y, start[2][i]];
+            for j in 0..3 {
+                assert!((p[j][i]-expected[j]).abs() < 1e-3*rho_2.sqrt(), "{:?} vs {:?}", p, expected);
+                assert_eq!(axis[j][i], axis_start[j][i], "{:?}", axis);
+            }
+        }
+    }
+
+    /// A particle on the z axis doesn't change the path of the others in its batch
+    #[test]
+    fn mixed_batch() {
+        let wavefunc = circular(3);
+        let a = wavefunc.length_scale();
+        let mut batch: Pos<2> = [
+            SVector::from([9.0*a, 0.0]),
+            SVector::from([0.0, 0.0]),
+            SVector::from([1.8*a, 0.3]),
+        ];
+        let mut single: Pos<1> = [
+            SVector::from([9.0*a]),
+            SVector::from([0.0]),
+            SVector::from([1.8*a]),
+        ];
+        integrate(&wavefunc, &mut batch, 0.0, 50.0, 0.1, 1e-5);
+        integrate(&wavefunc, &mut single, 0.0, 50.0, 0.1, 1e-5);
+        for j in 0..3 {
+            assert!((batch[j][0]-single[j][0]).abs() <= 1e-4*a, "{:?} vs {:?}", batch, single);
+        }
+        assert_eq!([batch[0][1], batch[1][1], batch[2][1]], [0.0, 0.0, 0.3]);
+    }
+}
@@ -6,6 +6,7 @@ use web_sys::{
     WebGlVertexArrayObject,
     WebGlUniformLocation,
     WebGlFramebuffer,
+    WebGlRenderbuffer,
     WebGlTexture,
     WebGlBuffer,
 };
@@ -20,6 +21,8 @@ pub struct RenderPass {
     pub uniforms: FxHashMap<&'static str, WebGlUniformLocation>,
     pub attributes: FxHashMap<&'static str, u32>,
     pub draw_buffers: FxHashMap<&'static str, u32>,
+    /// Width and height of the framebuffer attachments
+    pub size: (i32, i32),
 }
 
 impl RenderPass {
@@ -65,7 +68,9 @@ impl RenderPass {
             None => FxHashMap::default(),
         };
 
-        Self {context, shader, fbos, vaos, uniforms, attributes, draw_buffers}
+        let size = (context.drawing_buffer_width(), context.drawing_buffer_height());
+
+        Self {context, shader, fbos, vaos, uniforms, attributes, draw_buffers, size}
     }
 
     pub fn buffer_alloc(
@@ -111,6 +116,52 @@ impl RenderPass {
         buffer
     }
 
+    pub fn buffer_sub_data(
+        &self,
+        buffer: &WebGlBuffer,
+        offset: i32,
+        data: &[f32],
+    ) {
+        let context = &self.context;
+
+        context.bind_buffer(Gl::ARRAY_BUFFER, Some(buffer));
+
+        unsafe {
+            let view = js_sys::Float32Array::view(data);
+            context.buffer_sub_data_with_i32_and_array_buffer_view(
+                Gl::ARRAY_BUFFER, offset, &view,
+            );
+        }
+
+        context.bind_buffer(Gl::ARRAY_BUFFER, None);
+    }
+
+    pub fn buffer_index(
+        &self,
+        data: &[u32],
+        hint: u32,
+    ) -> WebGlBuffer {
+        let context = &self.context;
+
+        let buffer = context
+            .create_buffer()
+            .expect_throw("err: create_buffer");
+        // Element array bindings are part of the vertex array state
+        context.bind_vertex_array(None);
+        context.bind_buffer(Gl::ELEMENT_ARRAY_BUFFER, Some(&buffer));
+
+        unsafe {
+            let view = js_sys::Uint32Array::view(data);
+            context.buffer_data_with_array_buffer_view(
+                Gl::ELEMENT_ARRAY_BUFFER, &view, hint,
+            );
+        }
+
+        context.bind_buffer(Gl::ELEMENT_ARRAY_BUFFER, None);
+
+        buffer
+    }
+
     pub fn vao_buffer(
         &self,
         vao: usize,
@@ -141,13 +192,26 @@ impl RenderPass {
         context.bind_vertex_array(None);
     }
 
+    pub fn vao_index_buffer(
+        &self,
+        vao: usize,
+        buffer: &WebGlBuffer,
+    ) {
+        let context = &self.context;
+
+        context.bind_vertex_array(self.vaos.get(vao));
+        context.bind_buffer(Gl::ELEMENT_ARRAY_BUFFER, Some(buffer));
+        context.bind_vertex_array(None);
+        context.bind_buffer(Gl::ELEMENT_ARRAY_BUFFER, None);
+    }
+
     pub fn fb_renderbuffer(
         &self,
         fbo: usize,
         samples: i32,
         format: u32,
         attachment: u32,
-    ) {
+    ) -> WebGlRenderbuffer {
         let context = &self.context;
 
         context.bind_framebuffer(Gl::FRAMEBUFFER, self.fbos.get(fbo));
@@ -159,8 +223,7 @@ impl RenderPass {
         context.renderbuffer_storage_multisample(
             Gl::RENDERBUFFER,
             samples, format,
-            context.drawing_buffer_width(),
-            context.drawing_buffer_height(),
+            self.size.0, self.size.1,
         );
 
         context.framebuffer_renderbuffer(
@@ -172,6 +235,8 @@ impl RenderPass {
 
         context.bind_renderbuffer(Gl::RENDERBUFFER, None);
         context.bind_framebuffer(Gl::FRAMEBUFFER, None);
+
+        rbo
     }
 
     pub fn fb_texture(
@@ -197,8 +262,7 @@ impl RenderPass {
         context.tex_storage_2d(
             Gl::TEXTURE_2D,
             1, format,
-            context.drawing_buffer_width(),
-            context.drawing_buffer_height(),
+            self.size.0, self.size.1,
         );
 
         context.framebuffer_texture_2d(
@@ -215,6 +279,105 @@ impl RenderPass {
         tex
     }
 
+    /// Texture of two-channel values, row by row
+    pub fn texture_rg(
+        &self,
+        unit: u32,
+        width: i32,
+        height: i32,
+        data: &[f32],
+    ) -> WebGlTexture {
+        let context = &self.context;
+
+        let tex = context
+            .create_texture()
+            .expect_throw("err: create_texture");
+        context.active_texture(unit);
+        context.bind_texture(Gl::TEXTURE_2D, Some(&tex));
+
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_MAG_FILTER, Gl::NEAREST as i32);
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_MIN_FILTER, Gl::NEAREST as i32);
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_WRAP_S, Gl::REPEAT as i32);
+        context.tex_parameteri(Gl::TEXTURE_2D, Gl::TEXTURE_WRAP_T, Gl::REPEAT as i32);
+        context.tex_storage_2d(
+            Gl::TEXTURE_2D,
+            1, Gl::RG16F,
+            width, height,
+        );
+
+        unsafe {
+            let view = js_sys::Float32Array::view(data);
+            context.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_array_buffer_view(
+                Gl::TEXTURE_2D, 0,
+                0, 0,
+                width, height,
+                Gl::RG, Gl::FLOAT,
+                Some(&view),
+            ).expect_throw("err: tex_sub_image_2d");
+        }
+
+        context.bind_texture(Gl::TEXTURE_2D, None);
+
+        tex
+    }
+
+    pub fn texture_3d(
+        &self,
+        unit: u32,
+        format: u32,
+        size: i32,
+    ) -> WebGlTexture {
+        let context = &self.context;
+
+        let tex = context
+            .create_texture()
+            .expect_throw("err: create_texture");
+        context.active_texture(unit);
+        context.bind_texture(Gl::TEXTURE_3D, Some(&tex));
+
+        context.tex_parameteri(Gl::TEXTURE_3D, Gl::TEXTURE_MAG_FILTER, Gl::LINEAR as i32);
+        context.tex_parameteri(Gl::TEXTURE_3D, Gl::TEXTURE_MIN_FILTER, Gl::LINEAR as i32);
+        context.tex_parameteri(Gl::TEXTURE_3D, Gl::TEXTURE_WRAP_S, Gl::CLAMP_TO_EDGE as i32);
+        context.tex_parameteri(Gl::TEXTURE_3D, Gl::TEXTURE_WRAP_T, Gl::CLAMP_TO_EDGE as i32);
+        context.tex_parameteri(Gl::TEXTURE_3D, Gl::TEXTURE_WRAP_R, Gl::CLAMP_TO_EDGE as i32);
+        context.tex_storage_3d(
+            Gl::TEXTURE_3D,
+            1, format,
+            size, size, size,
+        );
+
+        context.bind_texture(Gl::TEXTURE_3D, None);
+
+        tex
+    }
+
+    /// Uploads size³ single-channel values to a texture from texture_3d
+    pub fn texture_3d_data(
+        &self,
+        tex: &WebGlTexture,
+        unit: u32,
+        size: i32,
+        data: &[f32],
+    ) {
+        let context = &self.context;
+
+        context.active_texture(unit);
+        context.bind_texture(Gl::TEXTURE_3D, Some(tex));
+
+        unsafe {
+            let view = js_sys::Float32Array::view(data);
+            context.tex_sub_image_3d_with_opt_array_buffer_view(
+                Gl::TEXTURE_3D, 0,
+                0, 0, 0,
+                size, size, size,
+                Gl::RED, Gl::FLOAT,
+                Some(&view),
+            ).expect_throw("err: tex_sub_image_3d");
+        }
+
+        context.bind_texture(Gl::TEXTURE_3D, None);
+    }
+
     pub fn uniform_texture(
         &self,
         var: &str,
@@ -227,6 +390,18 @@ impl RenderPass {
         self.context.uniform1i(uniform, (unit - Gl::TEXTURE0) as i32);
     }
 
+    pub fn uniform_texture_3d(
+        &self,
+        var: &str,
+        val: &WebGlTexture,
+        unit: u32,
+    ) {
+        let uniform = self.uniforms.get(var);
+        self.context.active_texture(unit);
+        self.context.bind_texture(Gl::TEXTURE_3D, Some(val));
+        self.context.uniform1i(uniform, (unit - Gl::TEXTURE0) as i32);
+    }
+
     pub fn uniform_mat4(
         &self,
         var: &str,
@@ -258,6 +433,24 @@ impl RenderPass {
         self.context.uniform1f(uniform, val);
     }
 
+    pub fn uniform_vec4_array(
+        &self,
+        var: &str,
+        val: &[f32],
+    ) {
+        let uniform = self.uniforms.get(var);
+        self.context.uniform4fv_with_f32_array(uniform, val);
+    }
+
+    pub fn uniform_int(
+        &self,
+        var: &str,
+        val: i32,
+    ) {
+        let uniform = self.uniforms.get(var);
+        self.context.uniform1i(uniform, val);
+    }
+
     pub fn set_draw_buffers(
         &self,
         fbo: usize,
@@ -0,0 +1,121 @@
+use js_sys::Float32Array;
+use wasm_bindgen::prelude::*;
+use crate::wavefunc::{Psi, Basis, QuantumNumbers, M_PROTON};
+
+/// Radial and angular distributions of a hydrogen-like state,
+/// sampled at evenly spaced points for 2D charts
+#[wasm_bindgen]
+pub struct Distribution {
+    r: Vec<f32>,
+    radial: Vec<f32>,
+    radial_density: Vec<f32>,
+    cos_theta: Vec<f32>,
+    angular_density: Vec<f32>,
+    radial_nodes: Vec<f32>,
+    nodal_cones: Vec<f32>,
+    nodal_planes: Vec<f32>,
+    mean_radius: f32,
+    mean_square_radius: f32,
+    mean_inverse_radius: f32,
+}
+
+#[wasm_bindgen]
+impl Distribution {
+    /// Samples r over [0, extent] and cos(θ) over [-1, 1] at points each
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        n: u32,
+        l: u32,
+        m: i32,
+        real: bool,
+        charge: u32,
+        points: usize,
+    ) -> Result<Distribution, JsValue> {
+        let qn = QuantumNumbers::new(n, l, m).map_err(|e| JsValue::from_str(&e))?;
+        let psi = Psi::builder(qn)
+            .basis(if real {Basis::Real} else {Basis::Complex})
+            .charge(charge)
+            .reduced_mass(1.0, M_PROTON)
+            .build();
+        let points = points.max(2);
+        let grid = |a: f32, b: f32| (0..points)
+            .map(|i| a+(b-a)*i as f32/(points-1) as f32)
+            .collect::<Vec<_>>();
+        let r = grid(0.0, psi.extent());
+        let cos_theta = grid(-1.0, 1.0);
+
+        Ok(Self {
+            radial: r.iter().map(|&r| psi.radial(r)).collect(),
+            radial_density: r.iter().map(|&r| psi.radial_density(r)).collect(),
+            angular_density: cos_theta.iter().map(|&c| psi.angular_density(c)).collect(),
+            radial_nodes: psi.radial_nodes(),
+            nodal_cones: psi.nodal_cones(),
+            nodal_planes: psi.nodal_planes(),
+            mean_radius: psi.mean_radius(),
+            mean_square_radius: psi.mean_square_radius(),
+            mean_inverse_radius: psi.mean_inverse_radius(),
+            r, cos_theta,
+        })
+    }
+
+    pub fn r(&self) -> Float32Array {
+        Float32Array::from(&self.r[..])
+    }
+
+    /// R_nl(r)
+    pub fn radial(&self) -> Float32Array {
+        Float32Array::from(&self.radial[..])
+    }
+
+    /// P(r) = r²·R_nl(r)²
+    #[wasm_bindgen(js_name = radialDensity)]
+    pub fn radial_density(&self) -> Float32Array {
+        Float32Array::from(&self.radial_density[..])
+    }
+
+    #[wasm_bindgen(js_name = cosTheta)]
+    pub fn cos_theta(&self) -> Float32Array {
+        Float32Array::from(&self.cos_theta[..])
+    }
+
+    /// |Y_lm|² integrated over φ, as a density of cos(θ)
+    #[wasm_bindgen(js_name = angularDensity)]
+    pub fn angular_density(&self) -> Float32Array {
+        Float32Array::from(&self.angular_density[..])
+    }
+
+    #[wasm_bindgen(js_name = radialNodes)]
+    pub fn radial_nodes(&self) -> Float32Array {
+        Float32Array::from(&self.radial_nodes[..])
+    }
+
+    /// Polar angles θ of the nodal cones
+    #[wasm_bindgen(js_name = nodalCones)]
+    pub fn nodal_cones(&self) -> Float32Array {
+        Float32Array::from(&self.nodal_cones[..])
+    }
+
+    /// Azimuthal angles φ of the nodal planes of real orbitals
+    #[wasm_bindgen(js_name = nodalPlanes)]
+    pub fn nodal_planes(&self) -> Float32Array {
+        Float32Array::from(&self.nodal_planes[..])
+    }
+
+    /// ⟨r⟩ in Bohr radii
+    #[wasm_bindgen(getter, js_name = meanRadius)]
+    pub fn mean_radius(&self) -> f32 {
+        self.mean_radius
+    }
+
+    /// ⟨r²⟩ in squared Bohr radii
+    #[wasm_bindgen(getter, js_name = meanSquareRadius)]
+    pub fn mean_square_radius(&self) -> f32 {
+        self.mean_square_radius
+    }
+
+    /// ⟨1/r⟩ in inverse Bohr radii
+    #[wasm_bindgen(getter, js_name = meanInverseRadius)]
+    pub fn mean_inverse_radius(&self) -> f32 {
+        self.mean_inverse_radius
+    }
+}
@@ -0,0 +1,126 @@
+use std::ops::{Add, Sub, Mul, Div, Neg};
+use nalgebra::{Complex, ComplexField};
+
+type Cf32 = Complex<f32>;
+
+/// Dual-complex number a + Σ bᵢεᵢ with εᵢεⱼ = 0, carrying the partial
+/// derivatives with respect to three variables simultaneously
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Dual {
+    pub re: Cf32,
+    pub eps: [Cf32; 3],
+}
+
+impl Dual {
+    pub fn new(re: Cf32, eps: [Cf32; 3]) -> Self {
+        Self {re, eps}
+    }
+
+    pub fn constant(re: Cf32) -> Self {
+        Self::new(re, [Cf32::from(0.0); 3])
+    }
+
+    /// Independent variable i, i.e. ∂/∂xᵢ = 1
+    pub fn var(val: f32, i: usize) -> Self {
+        let mut eps = [Cf32::from(0.0); 3];
+        eps[i] = Cf32::from(1.0);
+        Self::new(Cf32::from(val), eps)
+    }
+
+    /// Chain rule, f(a + bε) = f(a) + f'(a)bε
+    pub fn chain(self, f: Cf32, df: Cf32) -> Self {
+        Self::new(f, self.eps.map(|e| e*df))
+    }
+
+    pub fn re_part(self) -> Self {
+        Self::new(Cf32::from(self.re.re), self.eps.map(|e| Cf32::from(e.re)))
+    }
+
+    pub fn im_part(self) -> Self {
+        Self::new(Cf32::from(self.re.im), self.eps.map(|e| Cf32::from(e.im)))
+    }
+
+    pub fn exp(self) -> Self {
+        let e = self.re.exp();
+        self.chain(e, e)
+    }
+
+    pub fn ln(self) -> Self {
+        self.chain(self.re.ln(), self.re.recip())
+    }
+
+    pub fn sqrt(self) -> Self {
+        let s = self.re.sqrt();
+        self.chain(s, 0.5/s)
+    }
+
+    pub fn powf(self, n: f32) -> Self {
+        if n == 0.0 {
+            Self::constant(Cf32::from(1.0))
+        } else {
+            self.chain(self.re.powf(n), self.re.powf(n-1.0)*n)
+        }
+    }
+
+    pub fn pow(self, n: Self) -> Self {
+        (n*self.ln()).exp()
+    }
+}
+
+impl Add for Dual {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re+rhs.re, [0, 1, 2].map(|i| self.eps[i]+rhs.eps[i]))
+    }
+}
+
+impl Add<Cf32> for Dual {
+    type Output = Self;
+    fn add(self, rhs: Cf32) -> Self {
+        Self::new(self.re+rhs, self.eps)
+    }
+}
+
+impl Sub for Dual {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self+(-rhs)
+    }
+}
+
+impl Neg for Dual {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self::new(-self.re, self.eps.map(|e| -e))
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl Mul for Dual {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re*rhs.re,
+            [0, 1, 2].map(|i| self.eps[i]*rhs.re+self.re*rhs.eps[i]),
+        )
+    }
+}
+
+impl Mul<Cf32> for Dual {
+    type Output = Self;
+    fn mul(self, rhs: Cf32) -> Self {
+        Self::new(self.re*rhs, self.eps.map(|e| e*rhs))
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl Div for Dual {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        let d = rhs.re*rhs.re;
+        Self::new(
+            self.re/rhs.re,
+            [0, 1, 2].map(|i| (self.eps[i]*rhs.re-self.re*rhs.eps[i])/d),
+        )
+    }
+}
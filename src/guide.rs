@@ -0,0 +1,236 @@
+use nalgebra::{Complex, Vector3};
+use crate::wavefunc::Superposition;
+
+type Cf32 = Complex<f32>;
+
+pub const MAX_TERMS: usize = 4;
+// Recurrence steps per polynomial, whose degree is at most n-1, for n ≤ 20
+pub const MAX_STEPS: usize = 19;
+
+/// Uniform data for the guiding equation in vert-xfb.glsl
+pub struct GuideUniforms {
+    pub n_terms: i32,
+    pub inv_mass: f32,
+    /// Per term (c, N, l-|m|, |m|)
+    pub term_a: Vec<f32>,
+    /// Per term (Re c_k, Im c_k, E_k, mode)
+    pub term_b: Vec<f32>,
+    /// Laguerre and Legendre recurrence steps (a, b, c, 0), MAX_STEPS per term,
+    /// padded with steps that leave the polynomial unchanged
+    pub lag: Vec<f32>,
+    pub leg: Vec<f32>,
+}
+
+impl GuideUniforms {
+    /// Fails for more than MAX_TERMS terms or more than MAX_STEPS recurrence
+    /// steps per polynomial, which the shader cannot hold
+    pub fn new(wavefunc: &Superposition) -> Result<Self, String> {
+        let terms = wavefunc.terms();
+        if terms.len() > MAX_TERMS {
+            return Err(format!("err: the guiding shader supports at most {} terms", MAX_TERMS));
+        }
+        let mut term_a = vec![0.0; MAX_TERMS*4];
+        let mut term_b = vec![0.0; MAX_TERMS*4];
+        let mut lag = [1.0, 0.0, 0.0, 0.0].repeat(MAX_TERMS*MAX_STEPS);
+        let mut leg = lag.clone();
+        let pack = |out: &mut [f32], steps: &[[f32; 3]]| {
+            for (o, s) in out.chunks_mut(4).zip(steps) {
+                o[..3].copy_from_slice(s);
+            }
+        };
+        for (t, (c, psi)) in terms.iter().enumerate() {
+            let f = psi.factors();
+            if f.lag.len().max(f.leg.len()) > MAX_STEPS {
+                return Err(format!("err: {} exceeds the polynomial degree of the guiding shader", psi.label()));
+            }
+            term_a[t*4..t*4+4].copy_from_slice(&[f.c, f.norm, f.l_m as f32, f.m_abs as f32]);
+            term_b[t*4..t*4+4].copy_from_slice(&[c.re, c.im, psi.energy(), f.mode as f32]);
+            pack(&mut lag[t*MAX_STEPS*4..], &f.lag);
+            pack(&mut leg[t*MAX_STEPS*4..], &f.leg);
+        }
+
+        Ok(Self {
+            n_terms: terms.len() as i32,
+            inv_mass: 1.0/wavefunc.reduced_mass(),
+            term_a, term_b, lag, leg,
+        })
+    }
+}
+
+// The functions below mirror vert-xfb.glsl statement by statement
+
+fn recurrence(steps: &[f32], t: usize, x: f32) -> (f32, f32) {
+    let (mut p, mut p_1) = (1.0, 0.0);
+    let (mut d, mut d_1) = (0.0, 0.0);
+    for i in 0..MAX_STEPS {
+        let s = &steps[(t*MAX_STEPS+i)*4..];
+        let q = (s[0]+s[1]*x)*p-s[2]*p_1;
+        let e = s[1]*p+(s[0]+s[1]*x)*d-s[2]*d_1;
+        p_1 = p;
+        p = q;
+        d_1 = d;
+        d = e;
+    }
+    (p, d)
+}
+
+fn psi_term(
+    u: &GuideUniforms,
+    t: usize,
+    p: &Vector3<f32>,
+) -> (Cf32, [Cf32; 3]) {
+    let a = &u.term_a[t*4..t*4+4];
+    let mode = u.term_b[t*4+3];
+    let (c, lm) = (a[0], a[2]);
+    let r = p.norm();
+    let dr = p/r;
+    let rho = c*r;
+    let (lag, lag_d) = recurrence(&u.lag, t, rho);
+    let e = (-0.5*rho).exp();
+    let rl = if lm > 0.0 {rho.powf(lm)} else {1.0};
+    let f = lag*e*rl;
+    let df = c*e*(rl*(lag_d-0.5*lag)+if lm > 0.0 {lm*rho.powf(lm-1.0)*lag} else {0.0});
+    let ct = p.z/r;
+    let dct = (Vector3::z()-dr*ct)/r;
+    let (leg, leg_d) = recurrence(&u.leg, t, ct);
+
+    let s = if mode == 1.0 {-1.0} else {1.0};
+    let w = Cf32::new(p.x, s*p.y)*c;
+    let mut h = Cf32::new(1.0, 0.0);
+    let mut dh = Cf32::new(0.0, 0.0);
+    for _ in 0..a[3] as i32 {
+        dh = dh*w+h;
+        h *= w;
+    }
+    let mut dhx = dh*c;
+    let mut dhy = dh*Cf32::new(0.0, s*c);
+    if mode == 2.0 {
+        h = Cf32::from(h.re);
+        dhx = Cf32::from(dhx.re);
+        dhy = Cf32::from(dhy.re);
+    } else if mode == 3.0 {
+        h = Cf32::from(h.im);
+        dhx = Cf32::from(dhx.im);
+        dhy = Cf32::from(dhy.im);
+    }
+
+    let fg = a[1]*f*leg;
+    let d_fg = (dr*(df*leg)+dct*(f*leg_d))*a[1];
+
+    (h*fg, [h*d_fg.x+dhx*fg, h*d_fg.y+dhy*fg, h*d_fg.z])
+}
+
+/// Shader equivalent of the guiding equation v = (ħ/μ) Im(∇ψ/ψ)
+pub fn velocity(
+    u: &GuideUniforms,
+    p: &Vector3<f32>,
+    time: f32,
+) -> Vector3<f32> {
+    let mut psi = Cf32::new(0.0, 0.0);
+    let mut grad = [psi; 3];
+    for t in 0..u.n_terms as usize {
+        let b = &u.term_b[t*4..t*4+4];
+        let phase = Cf32::new(b[0], b[1])*Cf32::new(0.0, -b[2]*time).exp();
+        let (v, d) = psi_term(u, t, p);
+        psi += v*phase;
+        for j in 0..3 {
+            grad[j] += d[j]*phase;
+        }
+    }
+    let n = psi.norm_sqr();
+    if n < 1e-30 {
+        return Vector3::zeros();
+    }
+
+    Vector3::from(grad.map(|g| (g.im*psi.re-g.re*psi.im)/n))*u.inv_mass
+}
+
+/// Shader equivalent of one transform feedback step
+pub fn rk4(
+    u: &GuideUniforms,
+    p: &Vector3<f32>,
+    time: f32,
+    dt: f32,
+) -> Vector3<f32> {
+    let k1 = velocity(u, p, time);
+    let k2 = velocity(u, &(p+k1*(0.5*dt)), time+0.5*dt);
+    let k3 = velocity(u, &(p+k2*(0.5*dt)), time+0.5*dt);
+    let k4 = velocity(u, &(p+k3*dt), time+dt);
+    p+(k1+k2*2.0+k3*2.0+k4)*(dt/6.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::SVector;
+    use crate::bohm;
+    use crate::wavefunc::{Psi, Basis, QuantumNumbers};
+
+    fn superposition(states: &[(u32, u32, i32, Basis)]) -> Superposition {
+        let terms = states.iter().enumerate()
+            .map(|(k, &(n, l, m, basis))| {
+                let c = Cf32::from_polar(1.0+0.3*k as f32, 0.7*k as f32);
+                (c, Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build())
+            })
+            .collect();
+        Superposition::new(terms).unwrap()
+    }
+
+    fn cases() -> Vec<Superposition> {
+        vec![
+            superposition(&[(2, 1, 1, Basis::Complex)]),
+            superposition(&[(3, 2, -1, Basis::Complex), (2, 1, 0, Basis::Complex)]),
+            superposition(&[(4, 3, 2, Basis::Real), (3, 1, -1, Basis::Real), (2, 0, 0, Basis::Complex)]),
+            superposition(&[
+                (5, 2, 1, Basis::Complex), (4, 1, -1, Basis::Complex),
+                (3, 2, 2, Basis::Real), (6, 4, -3, Basis::Complex),
+            ]),
+            superposition(&[(20, 0, 0, Basis::Complex)]),
+            superposition(&[(20, 19, -2, Basis::Real), (20, 7, 3, Basis::Complex)]),
+        ]
+    }
+
+    fn points(r: f32) -> Vec<Vector3<f32>> {
+        [[0.3, 0.5, -0.2], [-0.6, 0.1, 0.4], [0.2, -0.7, -0.5], [0.5, 0.5, 0.5]]
+            .into_iter()
+            .map(|p| Vector3::from(p)*r)
+            .collect()
+    }
+
+    #[test]
+    fn rejects_unsupported_states() {
+        let five = [(1, 0, 0), (2, 0, 0), (3, 0, 0), (4, 0, 0), (5, 0, 0)]
+            .map(|(n, l, m)| (n, l, m, Basis::Complex));
+        assert!(GuideUniforms::new(&superposition(&five)).is_err());
+        let n = MAX_STEPS as u32+2;
+        assert!(GuideUniforms::new(&superposition(&[(n, 0, 0, Basis::Complex)])).is_err());
+        assert!(GuideUniforms::new(&superposition(&[(n, n-1, 0, Basis::Complex)])).is_err());
+    }
+
+    /// The shader mirror agrees with the dual-number guiding equation
+    #[test]
+    fn matches_bohm() {
+        for wavefunc in cases() {
+            let u = GuideUniforms::new(&wavefunc).unwrap();
+            let (t, dt) = (0.8, 0.05);
+            for p in points(0.5*wavefunc.extent()) {
+                let q: bohm::Pos<1> = [0, 1, 2].map(|j| SVector::from([p[j]]));
+                let v = velocity(&u, &p, t);
+                let w = bohm::velocity(&wavefunc, &q, t);
+                let scale = v.norm().max(1e-3);
+                for j in 0..3 {
+                    assert!((v[j]-w[j][0]).abs() < 1e-3*scale, "{}: {} vs {:?}", wavefunc.label(), v, w);
+                }
+
+                let p_1 = rk4(&u, &p, t, dt);
+                let mut q_1 = q;
+                bohm::rk4(&wavefunc, &mut q_1, t, dt);
+                // Up to rounding of the positions themselves
+                let tol = 1e-3*scale*dt+4.0*f32::EPSILON*p.norm();
+                for j in 0..3 {
+                    assert!((p_1[j]-q_1[j][0]).abs() < tol, "{}: {} vs {:?}", wavefunc.label(), p_1, q_1);
+                }
+            }
+        }
+    }
+}
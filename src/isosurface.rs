@@ -0,0 +1,393 @@
+use nalgebra::{Complex, Vector3};
+use rustc_hash::FxHashMap;
+use crate::wavefunc::Superposition;
+
+type Vec3 = Vector3<f32>;
+
+/// Corner pairs of the cube edges, corner i at (i&1, i>>1&1, i>>2&1)
+const EDGES: [(usize, usize); 12] = [
+    (0, 1), (2, 3), (4, 5), (6, 7),
+    (0, 2), (1, 3), (4, 6), (5, 7),
+    (0, 4), (1, 5), (2, 6), (3, 7),
+];
+
+/// Scalar field sampled at the vertices of a cubic grid over [-half, half]³
+pub struct Grid {
+    pub n: usize,
+    pub half: f32,
+    pub values: Vec<f32>,
+}
+
+impl Grid {
+    /// Samples |ψ|², or ψ of a real state without the phase of its amplitude
+    /// if signed, at n³ points at time t
+    pub fn new(
+        wavefunc: &Superposition,
+        t: f32,
+        n: usize,
+        half: f32,
+        signed: bool,
+    ) -> Self {
+        let step = 2.0*half/(n-1) as f32;
+        let coord = |i: usize| -half+i as f32*step;
+        let mut values = Vec::with_capacity(n*n*n);
+        let mut psi = vec![Complex::default(); n*n];
+        let x = (0..n*n).map(|i| coord(i%n)).collect::<Vec<_>>();
+        let y = (0..n*n).map(|i| coord(i/n)).collect::<Vec<_>>();
+        let unit = wavefunc.real_phase().unwrap_or(Complex::from(1.0));
+        for k in 0..n {
+            let z = vec![coord(k); n*n];
+            wavefunc.eval_into(&x, &y, &z, t, &mut psi);
+            values.extend(psi.iter().map(|v| if signed {(v*unit).re} else {v.norm_sqr()}));
+        }
+
+        Self {n, half, values}
+    }
+
+    fn index(&self, i: usize, j: usize, k: usize) -> usize {
+        i+self.n*(j+self.n*k)
+    }
+
+    fn point(&self, i: usize, j: usize, k: usize) -> Vec3 {
+        let step = 2.0*self.half/(self.n-1) as f32;
+        Vec3::new(i as f32, j as f32, k as f32)*step-Vec3::repeat(self.half)
+    }
+
+    /// Central differences, one-sided at the boundary
+    fn gradient(&self, i: usize, j: usize, k: usize) -> Vec3 {
+        let p = [i, j, k];
+        Vec3::from_fn(|a, _| {
+            let (mut lo, mut hi) = (p, p);
+            lo[a] = p[a].saturating_sub(1);
+            hi[a] = (p[a]+1).min(self.n-1);
+            let d = self.values[self.index(hi[0], hi[1], hi[2])]
+                -self.values[self.index(lo[0], lo[1], lo[2])];
+            d/(hi[a]-lo[a]) as f32
+        })
+    }
+}
+
+/// Indexed triangle mesh with per-vertex normals and phases of ψ
+#[derive(Default)]
+pub struct Mesh {
+    pub vertices: Vec<f32>,
+    pub normals: Vec<f32>,
+    pub phases: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    fn vertex(&self, i: u32) -> Vec3 {
+        Vec3::from_column_slice(&self.vertices[3*i as usize..3*i as usize+3])
+    }
+
+    /// Phases of ψ at the vertices at time t
+    pub fn update_phases(&mut self, wavefunc: &Superposition, t: f32) {
+        let [x, y, z] = [0, 1, 2].map(|a| self.vertices.iter().skip(a).step_by(3).copied().collect::<Vec<_>>());
+        self.phases.resize(x.len(), 0.0);
+        wavefunc.phase_into(&x, &y, &z, t, &mut self.phases);
+    }
+
+    /// Replaces vertex normals which point against the area-weighted normal of
+    /// the adjacent faces, as the grid does not resolve the gradient close to
+    /// nodes. Corners of faces which still point against the vertex normal, at
+    /// creases where two sheets of the surface come closer than the grid
+    /// spacing, get a copy of the vertex with the normal of the face.
+    fn fit_normals(&mut self) {
+        let faces = self.indices.chunks_exact(3)
+            .map(|t| {
+                let [a, b, c] = [t[0], t[1], t[2]].map(|i| self.vertex(i));
+                (b-a).cross(&(c-a))
+            })
+            .collect::<Vec<_>>();
+        let mut adjacent = vec![Vec3::zeros(); self.vertices.len()/3];
+        for (t, face) in self.indices.chunks_exact(3).zip(&faces) {
+            for &i in t {
+                adjacent[i as usize] += face;
+            }
+        }
+        for (normal, face) in self.normals.chunks_exact_mut(3).zip(&adjacent) {
+            if Vec3::from_column_slice(normal).dot(face) < 0.0 {
+                normal.copy_from_slice(face.normalize().as_slice());
+            }
+        }
+        for (t, face) in faces.iter().enumerate() {
+            let Some(unit) = face.try_normalize(0.0) else { continue };
+            for corner in 3*t..3*t+3 {
+                let i = self.indices[corner] as usize;
+                if Vec3::from_column_slice(&self.normals[3*i..3*i+3]).dot(face) <= 0.0 {
+                    self.vertices.extend_from_within(3*i..3*i+3);
+                    self.normals.extend_from_slice(unit.as_slice());
+                    self.indices[corner] = (self.vertices.len()/3-1) as u32;
+                }
+            }
+        }
+    }
+}
+
+/// Isosurface enclosing a given probability. For real orbitals the
+/// positive and negative lobes of Re ψ are separate parts of the mesh.
+#[derive(Default)]
+pub struct Isosurface {
+    pub mesh: Mesh,
+    /// Index count of the positive part, the negative part follows
+    pub n_positive: usize,
+}
+
+impl Isosurface {
+    /// Surface of the region where |ψ|² is above the level that encloses
+    /// the given probability, on a grid of n³ points covering the extent.
+    /// Superpositions are taken at t = 0, as are the phases until
+    /// Mesh::update_phases.
+    pub fn new(
+        wavefunc: &Superposition,
+        probability: f32,
+        n: usize,
+    ) -> Self {
+        let signed = wavefunc.is_real();
+        let grid = Grid::new(wavefunc, 0.0, n, wavefunc.extent(), signed);
+        let density = grid.values.iter().map(|v| if signed {v*v} else {*v});
+        let level = enclosing_level(density, probability);
+        let table = case_table();
+
+        let mut mesh = Mesh::default();
+        let n_positive = if signed {
+            march(&grid, &table, level.sqrt(), 1.0, &mut mesh);
+            let n_positive = mesh.indices.len();
+            march(&grid, &table, level.sqrt(), -1.0, &mut mesh);
+            n_positive
+        } else {
+            march(&grid, &table, level, 1.0, &mut mesh);
+            mesh.indices.len()
+        };
+        mesh.fit_normals();
+        mesh.update_phases(wavefunc, 0.0);
+
+        Self {mesh, n_positive}
+    }
+}
+
+/// Density above which the grid points hold the given fraction of the total.
+/// The level lies halfway to the next lower grid value, as a level equal to a
+/// grid value would put vertices on grid points and leave degenerate triangles.
+fn enclosing_level(density: impl Iterator<Item = f32>, probability: f32) -> f32 {
+    let mut density = density.collect::<Vec<_>>();
+    density.sort_by(|a, b| b.total_cmp(a));
+    let total = density.iter().map(|&v| v as f64).sum::<f64>();
+    let mut sum = 0.0;
+    for (i, &v) in density.iter().enumerate() {
+        sum += v as f64;
+        if sum >= probability as f64*total {
+            let next = density[i..].iter().find(|&&w| w < v).copied().unwrap_or(0.0);
+            return 0.5*(v+next);
+        }
+    }
+    0.0
+}
+
+/// Polygons of crossed edges for each of the 256 cases of
+/// corners inside the surface. Contours on every face separate the inside
+/// corners and are chained into loops, so that neighbouring cubes always
+/// agree and the mesh is closed. Polygons wind counterclockwise seen from
+/// the outside.
+fn case_table() -> Vec<Vec<Vec<usize>>> {
+    let edge = |a: usize, b: usize| EDGES.iter()
+        .position(|&e| e == (a.min(b), a.max(b)))
+        .unwrap();
+    // Corners of each face counterclockwise seen from outside the cube
+    let faces = (0..3).flat_map(|w| {
+        let (u, v) = (1 << ((w+1)%3), 1 << ((w+2)%3));
+        [0, 1 << w].map(|base| {
+            let face = [base, base+u, base+u+v, base+v];
+            if base == 0 {[face[3], face[2], face[1], face[0]]} else {face}
+        })
+    }).collect::<Vec<_>>();
+
+    (0..256usize).map(|case| {
+        let inside = |c: usize| case >> c & 1 == 1;
+        // Contour segments from the edge entering an inside run of corners
+        // to the edge leaving it, in the order of the face corners
+        let mut next = [usize::MAX; 12];
+        for face in &faces {
+            let crossing = |i: usize| inside(face[i]) != inside(face[(i+1)%4]);
+            for i in (0..4).filter(|&i| crossing(i) && inside(face[(i+1)%4])) {
+                let j = (i+1..i+4).map(|j| j%4).find(|&j| crossing(j)).unwrap();
+                next[edge(face[i], face[(i+1)%4])] = edge(face[j], face[(j+1)%4]);
+            }
+        }
+        let mut polygons = Vec::new();
+        let mut visited = [false; 12];
+        for start in 0..12 {
+            if next[start] == usize::MAX || visited[start] {
+                continue;
+            }
+            let mut polygon = vec![start];
+            visited[start] = true;
+            let mut e = next[start];
+            while e != start {
+                polygon.push(e);
+                visited[e] = true;
+                e = next[e];
+            }
+            polygons.push(polygon);
+        }
+        polygons
+    }).collect()
+}
+
+/// Fan of triangles over a polygon of vertex indices, from the corner where no
+/// triangle folds over against the polygon, which may be far from planar
+fn triangulate(mesh: &mut Mesh, polygon: &[u32]) {
+    let p = polygon.iter().map(|&i| mesh.vertex(i)).collect::<Vec<_>>();
+    let k = p.len();
+    // Newell's normal of the polygon
+    let normal = (0..k).map(|i| p[i].cross(&p[(i+1)%k])).sum::<Vec3>();
+    let fan = |o: usize| (1..k-1).map(move |j| (o, (o+j)%k, (o+j+1)%k));
+    let origin = (0..k)
+        .max_by(|&a, &b| {
+            let fold = |o| fan(o)
+                .map(|(a, b, c)| (p[b]-p[a]).cross(&(p[c]-p[a])).dot(&normal))
+                .fold(f32::INFINITY, f32::min);
+            fold(a).total_cmp(&fold(b))
+        })
+        .unwrap();
+    for (a, b, c) in fan(origin) {
+        mesh.indices.extend([polygon[a], polygon[b], polygon[c]]);
+    }
+}
+
+/// Appends the surface sign·f = level, with normals along -sign·∇f
+fn march(
+    grid: &Grid,
+    table: &[Vec<Vec<usize>>],
+    level: f32,
+    sign: f32,
+    mesh: &mut Mesh,
+) {
+    let n = grid.n;
+    let corner = |i: usize, j: usize, k: usize, c: usize| (i+(c & 1), j+(c >> 1 & 1), k+(c >> 2 & 1));
+    // Vertices are shared between cubes, keyed by grid point and axis of the edge
+    let mut shared = FxHashMap::<usize, u32>::default();
+    for k in 0..n-1 {
+        for j in 0..n-1 {
+            for i in 0..n-1 {
+                let value = |c: usize| {
+                    let (x, y, z) = corner(i, j, k, c);
+                    sign*grid.values[grid.index(x, y, z)]
+                };
+                let case = (0..8).filter(|&c| value(c) > level).fold(0, |case, c| case | 1 << c);
+                for polygon in &table[case] {
+                    let corners = polygon.iter().map(|&e| {
+                        let (a, b) = EDGES[e];
+                        let (x, y, z) = corner(i, j, k, a);
+                        let key = grid.index(x, y, z)*3+(b-a).trailing_zeros() as usize;
+                        *shared.entry(key).or_insert_with(|| {
+                            let (u, v, w) = corner(i, j, k, b);
+                            let s = (level-value(a))/(value(b)-value(a));
+                            let p = grid.point(x, y, z).lerp(&grid.point(u, v, w), s);
+                            let g = grid.gradient(x, y, z).lerp(&grid.gradient(u, v, w), s);
+                            let normal = (-sign*g).try_normalize(0.0).unwrap_or_else(Vec3::z);
+                            mesh.vertices.extend_from_slice(p.as_slice());
+                            mesh.normals.extend_from_slice(normal.as_slice());
+                            (mesh.vertices.len()/3-1) as u32
+                        })
+                    }).collect::<Vec<_>>();
+                    triangulate(mesh, &corners);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wavefunc::{Basis, Psi, QuantumNumbers};
+
+    fn surface(states: &[(u32, u32, i32, Basis)], probability: f32) -> (Superposition, Isosurface) {
+        let terms = states.iter()
+            .map(|&(n, l, m, basis)| {
+                let psi = Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build();
+                (Complex::from(1.0), psi)
+            })
+            .collect();
+        let wavefunc = Superposition::new(terms).unwrap();
+        let surface = Isosurface::new(&wavefunc, probability, 64);
+        (wavefunc, surface)
+    }
+
+    fn cases() -> Vec<Vec<(u32, u32, i32, Basis)>> {
+        vec![
+            vec![(1, 0, 0, Basis::Complex)],
+            vec![(2, 1, 1, Basis::Real)],
+            vec![(3, 2, -2, Basis::Real)],
+            vec![(4, 1, 1, Basis::Complex)],
+            vec![(3, 2, 0, Basis::Complex), (2, 1, 1, Basis::Complex)],
+        ]
+    }
+
+    #[test]
+    fn closed() {
+        for states in cases() {
+            let (wavefunc, _) = surface(&states, 0.9);
+            let signed = wavefunc.is_real();
+            let grid = Grid::new(&wavefunc, 0.0, 64, wavefunc.extent(), signed);
+            let level = enclosing_level(grid.values.iter().map(|v| if signed {v*v} else {*v}), 0.9);
+            let (level, signs) = if signed {(level.sqrt(), &[1.0, -1.0][..])} else {(level, &[1.0][..])};
+            for &sign in signs {
+                let mut mesh = Mesh::default();
+                march(&grid, &case_table(), level, sign, &mut mesh);
+                let mut edges = FxHashMap::<(u32, u32), usize>::default();
+                for t in mesh.indices.chunks_exact(3) {
+                    for e in 0..3 {
+                        *edges.entry((t[e], t[(e+1)%3])).or_default() += 1;
+                    }
+                }
+                assert!(sign < 0.0 || !edges.is_empty());
+                for (&(a, b), &count) in &edges {
+                    assert_eq!((count, edges.get(&(b, a))), (1, Some(&1)),
+                        "{}: edge {} → {}", wavefunc.label(), a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn enclosing_radius() {
+        // 1 - e^(-2r)(1 + 2r + 2r²) of the 1s density e^(-2r)/π within r
+        let enclosed = |r: f64| 1.0-(-2.0*r).exp()*(1.0+2.0*r+2.0*r*r);
+        let (mut lo, mut hi) = (0.0, 10.0);
+        for _ in 0..60 {
+            let mid = 0.5*(lo+hi);
+            if enclosed(mid) < 0.9 {lo = mid} else {hi = mid}
+        }
+        let r = lo as f32;
+        let (wavefunc, surface) = surface(&[(1, 0, 0, Basis::Complex)], 0.9);
+        let step = 2.0*wavefunc.extent()/63.0;
+        let vertices = &surface.mesh.vertices;
+        for p in vertices.chunks_exact(3) {
+            let radius = Vec3::from_column_slice(p).norm();
+            assert!((radius-r).abs() < step, "vertex at r = {} vs {}", radius, r);
+        }
+        let mean = vertices.chunks_exact(3).map(|p| Vec3::from_column_slice(p).norm()).sum::<f32>()
+            /(vertices.len()/3) as f32;
+        assert!((mean-r).abs() < 0.1*step, "mean r = {} vs {}", mean, r);
+    }
+
+    #[test]
+    fn normals_match_winding() {
+        for states in cases() {
+            let (wavefunc, surface) = surface(&states, 0.9);
+            let mesh = &surface.mesh;
+            for t in mesh.indices.chunks_exact(3) {
+                let [a, b, c] = [t[0], t[1], t[2]].map(|i| mesh.vertex(i));
+                let face = (b-a).cross(&(c-a));
+                for &i in t.iter().filter(|_| face != Vec3::zeros()) {
+                    let normal = Vec3::from_column_slice(&mesh.normals[3*i as usize..3*i as usize+3]);
+                    assert!(normal.dot(&face) > 0.0,
+                        "{}: normal {:?} at {:?} against face {:?}", wavefunc.label(), normal, mesh.vertex(i), face);
+                }
+            }
+        }
+    }
+}
@@ -1,25 +1,243 @@
 #[macro_use]
 mod prelude; use prelude::*;
-mod wavefunc; use wavefunc::Psi;
+pub mod dual;
+pub mod bohm;
+pub mod guide; use guide::GuideUniforms;
+pub mod sampler; use sampler::{Diagnostics, Direct, Metropolis, MetropolisConfig, Regions};
+pub mod sequence; use sequence::Sobol;
+pub mod parallel;
+pub mod special;
+pub mod distribution;
+mod simd;
+#[cfg(feature = "threads")]
+use std::sync::mpsc;
+use std::f32::consts::PI;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+pub mod wavefunc; use wavefunc::{Psi, Basis, QuantumNumbers, Superposition, Nodes, M_PROTON};
 mod icosphere; use icosphere::IcoSphere;
+mod nodes; use nodes::NodeMesh;
+pub mod isosurface; use isosurface::{Grid, Isosurface};
 mod deferred; use deferred::RenderPass;
 
 static mut STATE: Option<RenderState> = None;
 
+// Atomic time units per second
+const TIME_SCALE: f64 = 4.0;
+// Largest integration step in atomic time units
+const MAX_DT: f64 = 0.1;
+// Particles re-sampled per frame while time evolution is active
+const RESAMPLE_CHUNK: usize = 2000;
+// Particles appended per batch while the instance buffer fills
+const STREAM_BATCH: usize = 1000;
+// Time per frame spent on filling the instance buffer in ms
+const STREAM_BUDGET: f64 = 8.0;
+// Particles per job on the thread pool
+#[cfg(feature = "threads")]
+const POOL_BATCH: usize = 8000;
+// Colour and opacity of the nodal surfaces
+const NODE_COLOR: [f32; 4] = [0.2, 0.4, 1.0, 0.25];
+// Colours of the positive and negative lobes of the isosurface
+const SURFACE_COLORS: [[f32; 4]; 2] = [[1.0, 0.5, 0.2, 1.0], [0.2, 0.5, 1.0, 1.0]];
+// Largest kernel of the ambient occlusion, as in frag-ssao.glsl
+const OCCLUSION_MAX_SAMPLES: usize = 64;
+// Side of the tiled rotation noise of the occlusion kernel
+const OCCLUSION_NOISE: usize = 4;
+// Falloff of the occlusion blur with depth differences per unit
+const OCCLUSION_SHARPNESS: f32 = 4.0;
+// Grid points per axis of the isosurface voxelization
+const ISO_GRID: usize = 64;
+// Grid points per axis of the density texture of the volume pass
+const VOLUME_GRID: usize = 48;
+// Ray-marching steps across the diagonal of the volume
+const VOLUME_STEPS: i32 = 192;
+
+/// Parameters of the screen-space ambient occlusion
+struct Occlusion {
+    // Kernel radius in view space
+    radius: f32,
+    // Occlusion of a fully covered point, 0 disables the pass
+    intensity: f32,
+    samples: usize,
+}
+
+static OCCLUSION: Mutex<Occlusion> = Mutex::new(Occlusion {
+    radius: 0.8,
+    intensity: 0.6,
+    samples: 16,
+});
+
+/// Sets the kernel radius, intensity and sample count of the ambient
+/// occlusion, with intensity 0 to turn it off
+#[wasm_bindgen(js_name = setOcclusion)]
+pub fn set_occlusion(radius: f32, intensity: f32, samples: u32) {
+    let mut occlusion = OCCLUSION.lock().unwrap_throw();
+    occlusion.radius = radius.max(0.0);
+    occlusion.intensity = intensity.max(0.0);
+    occlusion.samples = (samples as usize).clamp(1, OCCLUSION_MAX_SAMPLES);
+}
+
+static COLORMAP: AtomicU32 = AtomicU32::new(1);
+
+/// Colours particles and surfaces by the phase of ψ, with 0 for solid
+/// colours, 1 for a hue wheel and 2 for red and blue by the sign of Re ψ
+#[wasm_bindgen(js_name = setColormap)]
+pub fn set_colormap(map: u32) {
+    COLORMAP.store(map.min(2), Ordering::Relaxed);
+}
+
+static SHOW_NODES: AtomicBool = AtomicBool::new(false);
+
+/// Draws the nodal surfaces of single states as translucent meshes
+#[wasm_bindgen(js_name = showNodes)]
+pub fn show_nodes(show: bool) {
+    SHOW_NODES.store(show, Ordering::Relaxed);
+}
+
+static SHOW_VOLUME: AtomicBool = AtomicBool::new(false);
+
+/// Ray-marches the density instead of drawing particles
+#[wasm_bindgen(js_name = showVolume)]
+pub fn show_volume(show: bool) {
+    SHOW_VOLUME.store(show, Ordering::Relaxed);
+}
+
+/// Transfer function and optical properties of the volume
+struct VolumeOptics {
+    // Colour and opacity at densities 0, 1/3, 2/3 and 1 of the maximum
+    transfer: [f32; 16],
+    // Emission and absorption per half extent
+    emission: f32,
+    absorption: f32,
+}
+
+static VOLUME_OPTICS: Mutex<VolumeOptics> = Mutex::new(VolumeOptics {
+    transfer: [
+        0.2, 0.4, 1.0, 0.0,
+        0.2, 0.4, 1.0, 0.3,
+        0.6, 0.3, 0.9, 0.7,
+        1.0, 0.3, 0.3, 1.0,
+    ],
+    emission: 1.0,
+    absorption: 8.0,
+});
+
+/// Sets the colours and opacities of the volume at densities evenly spaced
+/// between 0 and the maximum, as 4 RGBA values
+#[wasm_bindgen(js_name = setVolumeTransfer)]
+pub fn set_volume_transfer(transfer: &[f32]) {
+    VOLUME_OPTICS.lock().unwrap_throw().transfer = transfer.try_into()
+        .expect_throw("err: transfer function needs 4 RGBA values");
+}
+
+/// Sets the emission and absorption of the volume per half extent
+#[wasm_bindgen(js_name = setVolumeOptics)]
+pub fn set_volume_optics(emission: f32, absorption: f32) {
+    let mut optics = VOLUME_OPTICS.lock().unwrap_throw();
+    optics.emission = emission.max(0.0);
+    optics.absorption = absorption.max(0.0);
+}
+
+// Bits of the enclosed probability as f32, 0 if off
+static ISO_PROBABILITY: AtomicU32 = AtomicU32::new(0);
+
+/// Draws the surface enclosing the given probability, e.g. 0.9, or none if 0
+#[wasm_bindgen(js_name = showIsosurface)]
+pub fn show_isosurface(probability: f32) {
+    let probability = if probability > 0.0 {probability.min(1.0)} else {0.0};
+    ISO_PROBABILITY.store(probability.to_bits(), Ordering::Relaxed);
+}
+
+// Diagnostics of the latest batch, only accessed from the main thread
+static DIAGNOSTICS: Mutex<Diagnostics> = Mutex::new(Diagnostics {acceptance: Vec::new()});
+
+/// Acceptance rates of the latest sampled batch, per chain of the Metropolis
+/// sampler or per region of the region sampler, empty for exact sampling
+#[wasm_bindgen(js_name = samplerAcceptance)]
+pub fn sampler_acceptance() -> Vec<f32> {
+    DIAGNOSTICS.lock().unwrap_throw().acceptance.clone()
+}
+
 struct RenderState {
     _frame: AnimationFrame,
     _listeners: Vec<EventListener>,
     xfb_pass: XFBPass,
     geometry_pass: GeometryPass,
+    node_pass: NodePass,
+    surface_pass: SurfacePass,
     ssao_pass: SSAOPass,
     blend_pass: BlendPass,
+    volume_pass: VolumePass,
     context: Gl,
     canvas: web_sys::HtmlCanvasElement,
     proj: Matrix4::<f32>,
     view: Matrix4::<f32>,
     orbit: Orbit::<f32>,
     orbiting: bool,
+    scale: f32,
     time: f64,
+    t: f64,
+    stream: Stream,
+}
+
+impl RenderState {
+    /// Matches the canvas to the window at the device pixel ratio, which
+    /// also changes with zoom or between displays, and recreates the render
+    /// targets at the new drawing buffer size
+    fn fit_canvas(&mut self) {
+        let window = web_sys::window().unwrap_throw();
+        let ratio = window.device_pixel_ratio();
+        let css_width = window
+            .inner_width().unwrap_throw()
+            .as_f64().unwrap_throw();
+        let css_height = window
+            .inner_height().unwrap_throw()
+            .as_f64().unwrap_throw();
+        let canvas_width = (css_width*ratio) as u32;
+        let canvas_height = (css_height*ratio) as u32;
+        // A hidden or collapsed window keeps the last size
+        if canvas_width == 0 || canvas_height == 0 {
+            return;
+        }
+        if self.canvas.width() == canvas_width && self.canvas.height() == canvas_height {
+            return;
+        }
+        self.canvas.set_width(canvas_width);
+        self.canvas.set_height(canvas_height);
+        let style = self.canvas.style();
+        style.set_property("width", &format!("{css_width}px")).unwrap_throw();
+        style.set_property("height", &format!("{css_height}px")).unwrap_throw();
+
+        // The drawing buffer may be smaller than requested
+        let width = self.context.drawing_buffer_width();
+        let height = self.context.drawing_buffer_height();
+        self.context.viewport(0, 0, width, height);
+        self.proj[(0, 0)] = self.proj[(1, 1)] / (css_width/css_height) as f32;
+        self.geometry_pass.resize(width, height);
+        self.ssao_pass.resize(width, height);
+        self.blend_pass.resize(width, height);
+    }
+}
+
+enum Source {
+    Direct(Direct, Sobol),
+    Regions(Regions, Sobol),
+    Metropolis(Metropolis, SmallRng),
+}
+
+/// Source, offset, instances and diagnostics of a finished pool job
+#[cfg(feature = "threads")]
+type Batch = (Source, usize, Vec<f32>, Diagnostics);
+
+/// Fills the instance buffer progressively, then keeps re-sampling it
+/// in chunks if the density changes in time
+struct Stream {
+    wavefunc: Arc<Superposition>,
+    source: Option<Source>,
+    filled: usize,
+    cursor: usize,
+    #[cfg(feature = "threads")]
+    job: Option<mpsc::Receiver<Batch>>,
 }
 
 struct XFBPass {
@@ -33,47 +251,87 @@ struct GeometryPass {
     rp: RenderPass,
     n_inst: usize,
     particle: IcoSphere,
+    depth: web_sys::WebGlRenderbuffer,
     t_color: web_sys::WebGlTexture,
     t_gdata: web_sys::WebGlTexture,
 }
+struct NodePass {
+    rp: RenderPass,
+    n_vert: usize,
+}
+struct SurfacePass {
+    rp: RenderPass,
+    probability: f32,
+    surface: Isosurface,
+    // Time of the phases in the buffer
+    t: f64,
+    buffers: Vec<web_sys::WebGlBuffer>,
+}
 struct SSAOPass {
     rp: RenderPass,
+    blur: RenderPass,
     t_occlusion: web_sys::WebGlTexture,
+    t_blur: web_sys::WebGlTexture,
+    t_noise: web_sys::WebGlTexture,
+    kernel: Vec<f32>,
+    rng: SmallRng,
 }
 struct BlendPass {
     rp: RenderPass,
+    color: web_sys::WebGlRenderbuffer,
+}
+struct VolumePass {
+    rp: RenderPass,
+    t_density: web_sys::WebGlTexture,
+    // Time of the texture contents, none before the first upload
+    t: Option<f64>,
 }
 
 impl XFBPass {
     pub fn new(
         context: Gl,
-        instances: &[f32],
         n_inst: usize,
+        guide: &GuideUniforms,
     ) -> Self {
         let rp = RenderPass::new(
             context, 0, 2,
             include_shader!("vert-xfb.glsl"),
             include_shader!("no-op.glsl"),
-            Some(&["u_dt"]), Some(&["i_pos"]),
+            Some(&[
+                "u_dt", "u_time", "u_inv_mass", "u_n_terms",
+                "u_term_a", "u_term_b", "u_lag", "u_leg",
+            ]),
+            Some(&["i_pos"]),
             None, Some(&["v_pos"]),
         );
-        let buf1 = rp.buffer_data(instances, Gl::STREAM_DRAW);
-        let buf2 = rp.buffer_alloc((n_inst*VEC3_SZ) as i32, Gl::STREAM_DRAW);
-        rp.vao_buffer(0, &buf1, "i_pos", 3, 0, 0, false, 0);
-        rp.vao_buffer(1, &buf2, "i_pos", 3, 0, 0, false, 0);
+        rp.active(0, 0);
+        rp.uniform_int("u_n_terms", guide.n_terms);
+        rp.uniform_float("u_inv_mass", guide.inv_mass);
+        rp.uniform_vec4_array("u_term_a", &guide.term_a);
+        rp.uniform_vec4_array("u_term_b", &guide.term_b);
+        rp.uniform_vec4_array("u_lag", &guide.lag);
+        rp.uniform_vec4_array("u_leg", &guide.leg);
+        let buf1 = rp.buffer_alloc((n_inst*VEC4_SZ) as i32, Gl::STREAM_DRAW);
+        let buf2 = rp.buffer_alloc((n_inst*VEC4_SZ) as i32, Gl::STREAM_DRAW);
+        rp.vao_buffer(0, &buf1, "i_pos", 4, 0, 0, false, 0);
+        rp.vao_buffer(1, &buf2, "i_pos", 4, 0, 0, false, 0);
         let buffers = vec![buf1, buf2];
 
         Self {rp, n_inst, read_idx: 0, write_idx: 1, buffers}
     }
 
+    /// Advances the first count instances
     pub fn render(
         &mut self,
+        count: usize,
+        t: f32,
         dt: f32,
     ) {
         let context = &self.rp.context;
         let rp = &self.rp;
 
         rp.active(0, self.read_idx);
+        rp.uniform_float("u_time", t);
         rp.uniform_float("u_dt", dt);
 
         context.bind_buffer_base(
@@ -82,7 +340,7 @@ impl XFBPass {
         );
         context.enable(Gl::RASTERIZER_DISCARD);
         context.begin_transform_feedback(Gl::POINTS);
-        context.draw_arrays(Gl::POINTS, 0, self.n_inst as i32);
+        context.draw_arrays(Gl::POINTS, 0, count as i32);
         context.end_transform_feedback();
         context.disable(Gl::RASTERIZER_DISCARD);
         context.bind_buffer_base(
@@ -92,6 +350,158 @@ impl XFBPass {
 
         mem::swap(&mut self.read_idx, &mut self.write_idx);
     }
+
+    /// Writes instances (x, y, z, arg ψ) from offset on
+    pub fn update(
+        &self,
+        offset: usize,
+        instances: &[f32],
+    ) {
+        self.rp.buffer_sub_data(
+            &self.buffers[self.read_idx],
+            (offset*VEC4_SZ) as i32,
+            instances,
+        );
+    }
+}
+
+impl Source {
+    pub fn sample(
+        &mut self,
+        wavefunc: &Superposition,
+        t: f32,
+        out: &mut [f32],
+    ) -> Diagnostics {
+        match self {
+            Source::Direct(sampler, gen) => {
+                sampler.sample(gen, out);
+                Diagnostics::default()
+            },
+            Source::Regions(sampler, gen) => sampler.sample(wavefunc, t, gen, out),
+            Source::Metropolis(sampler, gen) => sampler.sample(wavefunc, t, gen, out),
+        }
+    }
+}
+
+impl Stream {
+    pub fn new(
+        wavefunc: Superposition,
+        rng: &mut SmallRng,
+    ) -> Self {
+        // Quasi-random candidates give even clouds at low particle counts,
+        // a changing density is followed by persistent Markov chains
+        let source = match wavefunc.terms() {
+            [(_, psi)] => Source::Direct(Direct::new(psi), Sobol::new(rng)),
+            _ if wavefunc.is_stationary() => Source::Regions(
+                Regions::symmetric(&wavefunc),
+                Sobol::new(rng),
+            ),
+            _ => Source::Metropolis(
+                Metropolis::new(MetropolisConfig::new(&wavefunc), &wavefunc, rng),
+                SmallRng::from_rng(rng).unwrap_throw(),
+            ),
+        };
+
+        Self {
+            wavefunc: Arc::new(wavefunc),
+            source: Some(source),
+            filled: 0,
+            cursor: 0,
+            #[cfg(feature = "threads")]
+            job: None,
+        }
+    }
+
+    /// Offset and length of the next batch of instances to sample, if any
+    fn next(&self, n_inst: usize, batch: usize) -> Option<(usize, usize)> {
+        if self.filled < n_inst {
+            Some((self.filled, batch.min(n_inst-self.filled)))
+        } else if !self.wavefunc.is_stationary() {
+            Some((self.cursor, RESAMPLE_CHUNK.min(n_inst-self.cursor)))
+        } else {
+            None
+        }
+    }
+
+    fn advance(&mut self, len: usize, n_inst: usize) {
+        if self.filled < n_inst {
+            self.filled += len;
+        } else {
+            self.cursor = (self.cursor+len)%n_inst;
+        }
+    }
+
+    pub fn step(
+        &mut self,
+        t: f64,
+        xfb_pass: &XFBPass,
+    ) {
+        #[cfg(feature = "threads")]
+        if parallel::pool_ready() {
+            return self.step_pooled(t, xfb_pass);
+        }
+        let n_inst = xfb_pass.n_inst;
+        let start = js_sys::Date::now();
+        while let Some((offset, len)) = self.next(n_inst, STREAM_BATCH) {
+            let mut positions = vec![0.0; len*3];
+            let diagnostics = self.source.as_mut().unwrap_throw().sample(&self.wavefunc, t as f32, &mut positions);
+            *DIAGNOSTICS.lock().unwrap_throw() = diagnostics;
+            let instances = with_phase(&self.wavefunc, t as f32, &positions);
+            xfb_pass.update(offset, &instances);
+            self.advance(len, n_inst);
+            // Filling continues within the budget, re-sampling takes one chunk per frame
+            if self.filled == n_inst || js_sys::Date::now()-start >= STREAM_BUDGET { break }
+        }
+    }
+
+    /// Samples on the thread pool, as the browser main thread must not block.
+    /// The source moves to a worker and returns with the finished batch.
+    #[cfg(feature = "threads")]
+    fn step_pooled(
+        &mut self,
+        t: f64,
+        xfb_pass: &XFBPass,
+    ) {
+        let n_inst = xfb_pass.n_inst;
+        if let Some(job) = &self.job {
+            let (source, offset, instances, diagnostics) = match job.try_recv() {
+                Ok(done) => done,
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => panic!("err: sampling worker failed"),
+            };
+            xfb_pass.update(offset, &instances);
+            *DIAGNOSTICS.lock().unwrap_throw() = diagnostics;
+            self.advance(instances.len()/4, n_inst);
+            self.source = Some(source);
+            self.job = None;
+        }
+        let Some((offset, len)) = self.next(n_inst, POOL_BATCH) else { return };
+        let mut source = self.source.take().unwrap_throw();
+        let wavefunc = self.wavefunc.clone();
+        let (tx, rx) = mpsc::channel();
+        rayon::spawn(move || {
+            let mut positions = vec![0.0; len*3];
+            let diagnostics = source.sample(&wavefunc, t as f32, &mut positions);
+            let instances = with_phase(&wavefunc, t as f32, &positions);
+            tx.send((source, offset, instances, diagnostics)).ok();
+        });
+        self.job = Some(rx);
+    }
+}
+
+/// Instances (x, y, z, arg ψ) from sampled positions
+fn with_phase(
+    wavefunc: &Superposition,
+    t: f32,
+    positions: &[f32],
+) -> Vec<f32> {
+    let [x, y, z] = [0, 1, 2].map(|a| positions.iter().skip(a).step_by(3).copied().collect::<Vec<_>>());
+    let mut phase = vec![0.0; x.len()];
+    wavefunc.phase_into(&x, &y, &z, t, &mut phase);
+    positions.chunks_exact(3)
+        .zip(phase)
+        .flat_map(|(p, phase)| [p[0], p[1], p[2], phase])
+        .collect()
 }
 
 impl GeometryPass {
@@ -104,8 +514,8 @@ impl GeometryPass {
         let rp = RenderPass::new(
             context, 1, 2,
             include_shader!("vert-g.glsl"),
-            include_shader!("frag-g.glsl"),
-            Some(&["u_proj", "u_view", "u_scale", "u_lightdir"]),
+            include_shader!("colormap.glsl", "frag-g.glsl"),
+            Some(&["u_proj", "u_view", "u_scale", "u_lightdir", "u_colormap"]),
             Some(&["i_pos", "a_pos", "a_normal"]),
             Some(&["o_color", "o_gdata"]),
             None,
@@ -114,11 +524,22 @@ impl GeometryPass {
         let buf_g = rp.buffer_data(particle.vertex_buf().as_slice(), Gl::STATIC_DRAW);
         let buf_n = rp.buffer_data(particle.normal_buf().as_slice(), Gl::STATIC_DRAW);
         for i in 0..2 {
-            rp.vao_buffer(i, &buf_i[i], "i_pos", 3, 0, 0, false, 1);
+            rp.vao_buffer(i, &buf_i[i], "i_pos", 4, 0, 0, false, 1);
             rp.vao_buffer(i, &buf_g, "a_pos", 3, 0, 0, false, 0);
             rp.vao_buffer(i, &buf_n, "a_normal", 3, 0, 0, true, 0);
         }
-        rp.fb_renderbuffer(
+        let (depth, t_color, t_gdata) = Self::targets(&rp);
+        rp.set_draw_buffers(
+            0, &[Gl::COLOR_ATTACHMENT0, Gl::COLOR_ATTACHMENT1],
+        );
+
+        Self {rp, n_inst, particle, depth, t_color, t_gdata}
+    }
+
+    fn targets(
+        rp: &RenderPass,
+    ) -> (web_sys::WebGlRenderbuffer, web_sys::WebGlTexture, web_sys::WebGlTexture) {
+        let depth = rp.fb_renderbuffer(
             0, 0,
             Gl::DEPTH_COMPONENT16,
             Gl::DEPTH_ATTACHMENT,
@@ -135,16 +556,29 @@ impl GeometryPass {
             Gl::RGBA16F,
             Gl::COLOR_ATTACHMENT1,
         );
-        rp.set_draw_buffers(
-            0, &[Gl::COLOR_ATTACHMENT0, Gl::COLOR_ATTACHMENT1],
-        );
 
-        Self {rp, n_inst, particle, t_color, t_gdata}
+        (depth, t_color, t_gdata)
+    }
+
+    /// Recreates the G-buffer at the given size
+    pub fn resize(
+        &mut self,
+        width: i32,
+        height: i32,
+    ) {
+        let context = &self.rp.context;
+        context.delete_renderbuffer(Some(&self.depth));
+        context.delete_texture(Some(&self.t_color));
+        context.delete_texture(Some(&self.t_gdata));
+        self.rp.size = (width, height);
+        (self.depth, self.t_color, self.t_gdata) = Self::targets(&self.rp);
     }
 
+    /// Draws the first count instances
     pub fn render(
         &self,
         read_idx: usize,
+        count: usize,
         scale: f32,
         lightdir: &Vector3<f32>,
         proj: &Matrix4<f32>,
@@ -158,6 +592,7 @@ impl GeometryPass {
         rp.uniform_vec3("u_lightdir", lightdir);
         rp.uniform_mat4("u_proj", proj);
         rp.uniform_mat4("u_view", view);
+        rp.uniform_int("u_colormap", COLORMAP.load(Ordering::Relaxed) as i32);
 
         context.enable(Gl::DEPTH_TEST);
         context.clear_bufferfv_with_f32_array(
@@ -172,59 +607,329 @@ impl GeometryPass {
         context.draw_arrays_instanced(
             Gl::TRIANGLES, 0,
             self.particle.n_vert as i32,
-            self.n_inst as i32,
+            count.min(self.n_inst) as i32,
+        );
+    }
+}
+
+impl NodePass {
+    pub fn new(
+        context: Gl,
+        wavefunc: &Superposition,
+    ) -> Self {
+        let rp = RenderPass::new(
+            context, 0, 1,
+            include_shader!("vert-node.glsl"),
+            include_shader!("frag-node.glsl"),
+            Some(&["u_proj", "u_view", "u_scale", "u_lightdir", "u_color"]),
+            Some(&["a_pos", "a_normal"]),
+            Some(&["o_color"]),
+            None,
+        );
+        // The nodes of |ψ|² are only known for single states
+        let mesh = match wavefunc.terms() {
+            [(_, psi)] => NodeMesh::new(&psi.nodes(), psi.extent()),
+            _ => NodeMesh::new(&Nodes::default(), 0.0),
+        };
+        let buf_g = rp.buffer_data(mesh.vertex_buf(), Gl::STATIC_DRAW);
+        let buf_n = rp.buffer_data(mesh.normal_buf(), Gl::STATIC_DRAW);
+        rp.vao_buffer(0, &buf_g, "a_pos", 3, 0, 0, false, 0);
+        rp.vao_buffer(0, &buf_n, "a_normal", 3, 0, 0, true, 0);
+        rp.active(0, 0);
+        rp.uniform_vec4_array("u_color", &NODE_COLOR);
+
+        Self {rp, n_vert: mesh.n_vert}
+    }
+
+    /// Blends the surfaces into the colour target of the geometry pass,
+    /// hidden behind particles but not hiding them
+    pub fn render(
+        &self,
+        geometry_pass: &GeometryPass,
+        scale: f32,
+        lightdir: &Vector3<f32>,
+        proj: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+    ) {
+        if self.n_vert == 0 {
+            return;
+        }
+        let context = &self.rp.context;
+        let rp = &self.rp;
+
+        geometry_pass.rp.set_draw_buffers(0, &[Gl::COLOR_ATTACHMENT0, Gl::NONE]);
+        rp.active(0, 0);
+        context.bind_framebuffer(Gl::FRAMEBUFFER, geometry_pass.rp.fbos.first());
+        rp.uniform_float("u_scale", scale);
+        rp.uniform_vec3("u_lightdir", lightdir);
+        rp.uniform_mat4("u_proj", proj);
+        rp.uniform_mat4("u_view", view);
+
+        context.enable(Gl::DEPTH_TEST);
+        context.depth_mask(false);
+        context.disable(Gl::CULL_FACE);
+        context.enable(Gl::BLEND);
+        context.blend_func_separate(
+            Gl::SRC_ALPHA, Gl::ONE_MINUS_SRC_ALPHA,
+            Gl::ZERO, Gl::ONE,
         );
+        context.draw_arrays(Gl::TRIANGLES, 0, self.n_vert as i32);
+        context.disable(Gl::BLEND);
+        context.enable(Gl::CULL_FACE);
+        context.depth_mask(true);
+        geometry_pass.rp.set_draw_buffers(0, &[Gl::COLOR_ATTACHMENT0, Gl::COLOR_ATTACHMENT1]);
+    }
+}
+
+impl SurfacePass {
+    pub fn new(
+        context: Gl,
+    ) -> Self {
+        let rp = RenderPass::new(
+            context, 0, 1,
+            include_shader!("vert-surface.glsl"),
+            include_shader!("colormap.glsl", "frag-surface.glsl"),
+            Some(&["u_proj", "u_view", "u_scale", "u_lightdir", "u_color", "u_colormap"]),
+            Some(&["a_pos", "a_normal", "a_phase"]),
+            Some(&["o_color", "o_gdata"]),
+            None,
+        );
+
+        Self {rp, probability: 0.0, surface: Isosurface::default(), t: 0.0, buffers: Vec::new()}
+    }
+
+    /// Re-extracts the mesh if the enclosed probability changed, and updates
+    /// the phases at time t unless the state is real
+    pub fn update(
+        &mut self,
+        wavefunc: &Superposition,
+        probability: f32,
+        t: f64,
+    ) {
+        if probability != self.probability {
+            let rp = &self.rp;
+            for buffer in self.buffers.drain(..) {
+                rp.context.delete_buffer(Some(&buffer));
+            }
+            self.surface = Isosurface::new(wavefunc, probability, ISO_GRID);
+            let mesh = &self.surface.mesh;
+            let buf_g = rp.buffer_data(&mesh.vertices, Gl::STATIC_DRAW);
+            let buf_n = rp.buffer_data(&mesh.normals, Gl::STATIC_DRAW);
+            let buf_p = rp.buffer_data(&mesh.phases, Gl::DYNAMIC_DRAW);
+            let buf_i = rp.buffer_index(&mesh.indices, Gl::STATIC_DRAW);
+            rp.vao_buffer(0, &buf_g, "a_pos", 3, 0, 0, false, 0);
+            rp.vao_buffer(0, &buf_n, "a_normal", 3, 0, 0, true, 0);
+            rp.vao_buffer(0, &buf_p, "a_phase", 1, 0, 0, false, 0);
+            rp.vao_index_buffer(0, &buf_i);
+            self.buffers = vec![buf_g, buf_n, buf_p, buf_i];
+            self.probability = probability;
+            self.t = 0.0;
+        }
+        if t != self.t && !wavefunc.is_real() && !self.buffers.is_empty() {
+            self.surface.mesh.update_phases(wavefunc, t as f32);
+            self.rp.buffer_sub_data(&self.buffers[2], 0, &self.surface.mesh.phases);
+            self.t = t;
+        }
+    }
+
+    /// Draws the surface into the G-buffer of the geometry pass
+    pub fn render(
+        &self,
+        geometry_pass: &GeometryPass,
+        scale: f32,
+        lightdir: &Vector3<f32>,
+        proj: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+    ) {
+        let n_index = self.surface.mesh.indices.len();
+        if n_index == 0 {
+            return;
+        }
+        let context = &self.rp.context;
+        let rp = &self.rp;
+
+        rp.active(0, 0);
+        context.bind_framebuffer(Gl::FRAMEBUFFER, geometry_pass.rp.fbos.first());
+        rp.uniform_float("u_scale", scale);
+        rp.uniform_vec3("u_lightdir", lightdir);
+        rp.uniform_mat4("u_proj", proj);
+        rp.uniform_mat4("u_view", view);
+
+        rp.uniform_int("u_colormap", COLORMAP.load(Ordering::Relaxed) as i32);
+
+        context.enable(Gl::DEPTH_TEST);
+        let n_positive = self.surface.n_positive;
+        let parts = [(0, n_positive), (n_positive, n_index)];
+        for ((start, end), color) in parts.into_iter().zip(SURFACE_COLORS) {
+            if end > start {
+                rp.uniform_vec4_array("u_color", &color);
+                context.draw_elements_with_i32(
+                    Gl::TRIANGLES, (end-start) as i32,
+                    Gl::UNSIGNED_INT, (start*4) as i32,
+                );
+            }
+        }
     }
 }
 
 impl SSAOPass {
     pub fn new(
         context: Gl,
+        rng: &mut SmallRng,
     ) -> Self {
         let rp = RenderPass::new(
-            context, 1, 1,
+            context.clone(), 1, 1,
             include_shader!("vert-quad.glsl"),
             include_shader!("frag-ssao.glsl"),
-            Some(&["s_gdata", "u_width", "u_height"]),
+            Some(&[
+                "s_gdata", "s_noise", "u_proj", "u_width", "u_height",
+                "u_radius", "u_intensity", "u_samples", "u_kernel",
+            ]),
             Some(&["a_pos"]),
             Some(&["o_occlusion"]),
             None,
         );
-        let buf_g = rp.buffer_data(
-            &[
-                -1.0, 1.0, -1.0, -1.0,
-                1.0, -1.0, -1.0, 1.0,
-                1.0, -1.0, 1.0, 1.0,
-            ],
-            Gl::STATIC_DRAW,
+        let blur = RenderPass::new(
+            context, 1, 1,
+            include_shader!("vert-quad.glsl"),
+            include_shader!("frag-blur.glsl"),
+            Some(&["s_occlusion", "s_gdata", "u_axis", "u_sharpness"]),
+            Some(&["a_pos"]),
+            Some(&["o_occlusion"]),
+            None,
         );
-        rp.vao_buffer(0, &buf_g, "a_pos", 2, 0, 0, false, 0);
+        for rp in [&rp, &blur] {
+            let buf_g = rp.buffer_data(
+                &[
+                    -1.0, 1.0, -1.0, -1.0,
+                    1.0, -1.0, -1.0, 1.0,
+                    1.0, -1.0, 1.0, 1.0,
+                ],
+                Gl::STATIC_DRAW,
+            );
+            rp.vao_buffer(0, &buf_g, "a_pos", 2, 0, 0, false, 0);
+        }
+        let (t_occlusion, t_blur) = Self::targets(&rp, &blur);
+        // Random directions in the xy-plane rotate the kernel per pixel
+        let noise = (0..OCCLUSION_NOISE*OCCLUSION_NOISE)
+            .flat_map(|_| {
+                let phi = rng.gen_range(0.0..2.0*PI);
+                [phi.cos(), phi.sin()]
+            })
+            .collect::<Vec<_>>();
+        let t_noise = rp.texture_rg(
+            Gl::TEXTURE2,
+            OCCLUSION_NOISE as i32, OCCLUSION_NOISE as i32,
+            &noise,
+        );
+
+        Self {
+            rp, blur, t_occlusion, t_blur, t_noise,
+            kernel: Vec::new(),
+            rng: SmallRng::from_rng(rng).unwrap_throw(),
+        }
+    }
+
+    fn targets(
+        rp: &RenderPass,
+        blur: &RenderPass,
+    ) -> (web_sys::WebGlTexture, web_sys::WebGlTexture) {
         let t_occlusion = rp.fb_texture(
             0,
             Gl::TEXTURE3,
             Gl::R16F,
             Gl::COLOR_ATTACHMENT0,
         );
+        let t_blur = blur.fb_texture(
+            0,
+            Gl::TEXTURE5,
+            Gl::R16F,
+            Gl::COLOR_ATTACHMENT0,
+        );
+
+        (t_occlusion, t_blur)
+    }
+
+    /// Recreates the occlusion and blur targets at the given size
+    pub fn resize(
+        &mut self,
+        width: i32,
+        height: i32,
+    ) {
+        let context = &self.rp.context;
+        context.delete_texture(Some(&self.t_occlusion));
+        context.delete_texture(Some(&self.t_blur));
+        self.rp.size = (width, height);
+        self.blur.size = (width, height);
+        (self.t_occlusion, self.t_blur) = Self::targets(&self.rp, &self.blur);
+    }
 
-        Self {rp, t_occlusion}
+    /// Points in the unit hemisphere around +z, scaled to lie closer to
+    /// the centre as the index increases
+    fn kernel(&mut self, samples: usize) {
+        self.kernel = (0..samples)
+            .flat_map(|i| {
+                let v = Vector3::new(
+                    self.rng.gen_range(-1.0..1.0),
+                    self.rng.gen_range(-1.0..1.0),
+                    self.rng.gen_range(0.0..1.0),
+                ).try_normalize(0.0).unwrap_or_else(Vector3::z);
+                let a = i as f32/samples as f32;
+                let v = v*self.rng.gen::<f32>()*(0.1+0.9*a*a);
+                [v.x, v.y, v.z, 0.0]
+            })
+            .collect();
     }
 
+    /// Occlusion from the G-buffer, blurred along x and then y
     pub fn render(
-        &self,
+        &mut self,
         width: i32,
         height: i32,
         t_gdata: &web_sys::WebGlTexture,
+        proj: &Matrix4<f32>,
     ) {
+        let (radius, intensity, samples) = {
+            let occlusion = OCCLUSION.lock().unwrap_throw();
+            (occlusion.radius, occlusion.intensity, occlusion.samples)
+        };
+        if self.kernel.len() != 4*samples {
+            self.kernel(samples);
+        }
         let context = &self.rp.context;
+        if intensity <= 0.0 {
+            context.bind_framebuffer(Gl::FRAMEBUFFER, self.rp.fbos.first());
+            context.clear_bufferfv_with_f32_array(Gl::COLOR, 0, &[0.0, 0.0, 0.0, 0.0]);
+            return;
+        }
         let rp = &self.rp;
+        let blur = &self.blur;
 
         rp.active(0, 0);
         rp.uniform_texture("s_gdata", t_gdata, Gl::TEXTURE1);
+        rp.uniform_texture("s_noise", &self.t_noise, Gl::TEXTURE2);
+        rp.uniform_mat4("u_proj", proj);
         rp.uniform_float("u_width", width as f32);
         rp.uniform_float("u_height", height as f32);
+        rp.uniform_float("u_radius", radius);
+        rp.uniform_float("u_intensity", intensity);
+        rp.uniform_int("u_samples", samples as i32);
+        rp.uniform_vec4_array("u_kernel", &self.kernel);
 
         context.disable(Gl::DEPTH_TEST);
         context.draw_arrays(Gl::TRIANGLES, 0, 6);
+
+        blur.active(0, 0);
+        blur.uniform_texture("s_occlusion", &self.t_occlusion, Gl::TEXTURE3);
+        blur.uniform_texture("s_gdata", t_gdata, Gl::TEXTURE1);
+        blur.uniform_float("u_sharpness", OCCLUSION_SHARPNESS);
+        blur.uniform_int("u_axis", 0);
+        context.draw_arrays(Gl::TRIANGLES, 0, 6);
+
+        context.bind_framebuffer(Gl::FRAMEBUFFER, rp.fbos.first());
+        blur.uniform_texture("s_occlusion", &self.t_blur, Gl::TEXTURE5);
+        blur.uniform_int("u_axis", 1);
+        context.draw_arrays(Gl::TRIANGLES, 0, 6);
     }
 }
 
@@ -250,19 +955,34 @@ impl BlendPass {
             Gl::STATIC_DRAW,
         );
         rp.vao_buffer(0, &buf_g, "a_pos", 2, 0, 0, false, 0);
+        let color = Self::target(&rp);
+
+        Self {rp, color}
+    }
+
+    fn target(
+        rp: &RenderPass,
+    ) -> web_sys::WebGlRenderbuffer {
         rp.fb_renderbuffer(
             0, 4,
             Gl::RGBA8,
             Gl::COLOR_ATTACHMENT0,
-        );
+        )
+    }
 
-        Self {rp}
+    /// Recreates the multisampled output at the given size
+    pub fn resize(
+        &mut self,
+        width: i32,
+        height: i32,
+    ) {
+        self.rp.context.delete_renderbuffer(Some(&self.color));
+        self.rp.size = (width, height);
+        self.color = Self::target(&self.rp);
     }
 
     pub fn render(
         &self,
-        width: i32,
-        height: i32,
         t_color: &web_sys::WebGlTexture,
         t_occlusion: &web_sys::WebGlTexture,
     ) {
@@ -275,8 +995,16 @@ impl BlendPass {
 
         context.disable(Gl::DEPTH_TEST);
         context.draw_arrays(Gl::TRIANGLES, 0, 6);
+    }
 
-        //context.bind_framebuffer(Gl::READ_FRAMEBUFFER, Some(&s.fbo));
+    /// Copies the result to the canvas, which has the size of the targets
+    pub fn present(
+        &self,
+    ) {
+        let context = &self.rp.context;
+        let (width, height) = self.rp.size;
+
+        context.bind_framebuffer(Gl::READ_FRAMEBUFFER, self.rp.fbos.first());
         context.bind_framebuffer(Gl::DRAW_FRAMEBUFFER, None);
         context.blit_framebuffer(
             0, 0, width, height,
@@ -287,8 +1015,95 @@ impl BlendPass {
     }
 }
 
-#[wasm_bindgen(start)]
-pub fn main() {
+impl VolumePass {
+    pub fn new(
+        context: Gl,
+    ) -> Self {
+        let rp = RenderPass::new(
+            context, 0, 1,
+            include_shader!("vert-quad.glsl"),
+            include_shader!("frag-volume.glsl"),
+            Some(&[
+                "s_gdata", "s_density", "u_inv_proj", "u_inv_view",
+                "u_scale", "u_half", "u_steps",
+                "u_emission", "u_absorption", "u_transfer",
+            ]),
+            Some(&["a_pos"]),
+            Some(&["o_color"]),
+            None,
+        );
+        let buf_g = rp.buffer_data(
+            &[
+                -1.0, 1.0, -1.0, -1.0,
+                1.0, -1.0, -1.0, 1.0,
+                1.0, -1.0, 1.0, 1.0,
+            ],
+            Gl::STATIC_DRAW,
+        );
+        rp.vao_buffer(0, &buf_g, "a_pos", 2, 0, 0, false, 0);
+        let t_density = rp.texture_3d(Gl::TEXTURE4, Gl::R16F, VOLUME_GRID as i32);
+
+        Self {rp, t_density, t: None}
+    }
+
+    /// Voxelizes |ψ|² at time t relative to its maximum, once for
+    /// stationary states and every frame otherwise
+    pub fn update(
+        &mut self,
+        wavefunc: &Superposition,
+        t: f64,
+    ) {
+        if self.t.is_some() && wavefunc.is_stationary() {
+            return;
+        }
+        let grid = Grid::new(wavefunc, t as f32, VOLUME_GRID, wavefunc.extent(), false);
+        let max = grid.values.iter().fold(0.0, |max: f32, &v| max.max(v));
+        let density = grid.values.iter()
+            .map(|v| if max > 0.0 {v/max} else {0.0})
+            .collect::<Vec<_>>();
+        self.rp.texture_3d_data(&self.t_density, Gl::TEXTURE4, VOLUME_GRID as i32, &density);
+        self.t = Some(t);
+    }
+
+    /// Composites the emission and absorption along view rays over the
+    /// output of the blend pass, up to the geometry in the G-buffer
+    pub fn render(
+        &self,
+        blend_pass: &BlendPass,
+        wavefunc: &Superposition,
+        t_gdata: &web_sys::WebGlTexture,
+        scale: f32,
+        proj: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+    ) {
+        let context = &self.rp.context;
+        let rp = &self.rp;
+
+        rp.active(0, 0);
+        context.bind_framebuffer(Gl::FRAMEBUFFER, blend_pass.rp.fbos.first());
+        rp.uniform_texture("s_gdata", t_gdata, Gl::TEXTURE1);
+        rp.uniform_texture_3d("s_density", &self.t_density, Gl::TEXTURE4);
+        rp.uniform_mat4("u_inv_proj", &proj.try_inverse().unwrap_or_default());
+        rp.uniform_mat4("u_inv_view", &view.try_inverse().unwrap_or_default());
+        rp.uniform_float("u_scale", scale);
+        rp.uniform_float("u_half", wavefunc.extent());
+        rp.uniform_int("u_steps", VOLUME_STEPS);
+        let optics = VOLUME_OPTICS.lock().unwrap_throw();
+        rp.uniform_float("u_emission", optics.emission);
+        rp.uniform_float("u_absorption", optics.absorption);
+        rp.uniform_vec4_array("u_transfer", &optics.transfer);
+
+        context.disable(Gl::DEPTH_TEST);
+        context.enable(Gl::BLEND);
+        context.blend_func(Gl::ONE, Gl::ONE_MINUS_SRC_ALPHA);
+        context.draw_arrays(Gl::TRIANGLES, 0, 6);
+        context.disable(Gl::BLEND);
+    }
+}
+
+// Tests run under the harness of wasm-bindgen-test, which exports its own main
+#[cfg_attr(not(test), wasm_bindgen(start))]
+pub fn main() -> Result<(), JsValue> {
     #[cfg(debug_assertions)]
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
@@ -341,59 +1156,29 @@ pub fn main() {
     let particle_lod = 0;
     let num_inst = 100000;
 
-    let mut ins_buf = Vec::<f32>::with_capacity(num_inst*VEC3_SZ);
-    let mut instances = Vec::<SVectorSliceMut3>::with_capacity(num_inst);
-    unsafe {
-        ins_buf.set_len(ins_buf.capacity());
-        instances.set_len(instances.capacity());
-        ins_buf.fill(0.0);
-        for i in 0..num_inst {
-            let off = i*VEC3_SZ;
-            let ptr = ins_buf.as_mut_ptr();
-            let slice = slice::from_raw_parts_mut(ptr.add(off), VEC3_SZ);
-            instances[i] = SVectorSliceMut3::from_slice(slice);
-        }
-    }
+    let wavefunc = Superposition::new(vec![(
+        Complex::new(1.0, 0.0),
+        Psi::builder(
+            QuantumNumbers::new(4, 1, 1).unwrap_throw(),
+        ).basis(Basis::Complex).charge(1).reduced_mass(1.0, M_PROTON).build(),
+    )]).map_err(|e| JsValue::from_str(&e))?;
+    document.set_title(&wavefunc.label());
+    // Fit the hydrogen-equivalent extent to the view, ions appear at their relative size
+    let scale = 24.0*wavefunc.length_scale()/wavefunc.extent();
 
-    let mut rng1 = SmallRng::seed_from_u64(123456789);
-    //let mut rng2 = SmallRng::seed_from_u64(123456789);
-    let dist1 = Uniform::<f32>::from(-10.0..10.0).map(
-        |x| (x*0.32).sinh()
+    let mut rng = SmallRng::seed_from_u64(123456789);
+    // States beyond the limits of the guiding shader are rejected
+    let guide = GuideUniforms::new(&wavefunc).map_err(|e| JsValue::from_str(&e))?;
+    let node_pass = NodePass::new(
+        context.clone(),
+        &wavefunc,
     );
-    //let dist2 = Uniform::<f32>::from(0.0..1.0);
-    let mut r_iter = dist1.sample_iter(&mut rng1);
-    //let mut s_iter = dist2.sample_iter(&mut rng2);
-
-    let wavefunc = Psi::new(4, 1, 0);
-    let mut samples = 0;
-    const batch_size: usize = 1000;
-    while samples < num_inst {
-        let x = SVector::<f32, batch_size>::from_iterator(r_iter.by_ref());
-        let y = SVector::<f32, batch_size>::from_iterator(r_iter.by_ref());
-        let z = SVector::<f32, batch_size>::from_iterator(r_iter.by_ref());
-        //let r = SVector::<f32, batch_size>::from_iterator(s_iter.by_ref());
-        let psi = wavefunc.eval(&x, &y, &z).map(|x| x.norm_sqr());
-        //let max = psi.camax().powi(2);
-        let max = psi.max();
-        for j in 0..batch_size { unsafe {
-            if samples == num_inst { break }
-            //let val = (*psi.vget_unchecked(j)).re.powi(2);
-            let val = *psi.vget_unchecked(j);
-            //let rnd = *r.vget_unchecked(j);
-            if val/max >= 0.02 {
-                let inst = instances.get_unchecked_mut(samples);
-                inst.x += *x.vget_unchecked(j);
-                inst.y += *y.vget_unchecked(j);
-                inst.z += *z.vget_unchecked(j);
-                samples += 1;
-            }
-        }}
-    }
+    let stream = Stream::new(wavefunc, &mut rng);
 
     let xfb_pass = XFBPass::new(
         context.clone(),
-        ins_buf.as_slice(),
         num_inst,
+        &guide,
     );
     let geometry_pass = GeometryPass::new(
         context.clone(),
@@ -401,27 +1186,42 @@ pub fn main() {
         num_inst,
         particle_lod,
     );
+    let surface_pass = SurfacePass::new(
+        context.clone(),
+    );
     let ssao_pass = SSAOPass::new(
         context.clone(),
+        &mut rng,
     );
     let blend_pass = BlendPass::new(
         context.clone(),
     );
+    let volume_pass = VolumePass::new(
+        context.clone(),
+    );
 
     unsafe {
         STATE = Some(RenderState {
             _frame: request_animation_frame(render),
-            _listeners: setup_event_handlers(&window, &document),
+            _listeners: setup_event_handlers(&document),
             xfb_pass,
             geometry_pass,
+            node_pass,
+            surface_pass,
             ssao_pass,
             blend_pass,
+            volume_pass,
             context, canvas,
             proj, view, orbit,
             orbiting: false,
+            scale,
             time: 0.0,
+            t: 0.0,
+            stream,
         });
     };
+
+    Ok(())
 }
 
 fn render(mut time: f64) {
@@ -430,54 +1230,69 @@ fn render(mut time: f64) {
     time *= 0.001;
     let dt = time-s.time;
     s.time = time;
+    // Atomic time, with large frame gaps clamped to keep the integration stable
+    let dt_au = (dt*TIME_SCALE).min(MAX_DT);
+    s.t += dt_au;
 
+    s.fit_canvas();
     let width = s.context.drawing_buffer_width();
     let height = s.context.drawing_buffer_height();
-    let scale = 2.0;
     let lightdir = Vector3::<f32>::new(0.0, 1.0, 1.0);
 
+    let volume = SHOW_VOLUME.load(Ordering::Relaxed);
+
+    s.stream.step(s.t, &s.xfb_pass);
     s.xfb_pass.render(
-        dt as f32,
+        s.stream.filled,
+        s.t as f32,
+        dt_au as f32,
     );
+    // The G-buffer still holds surfaces that end the rays through the volume
     s.geometry_pass.render(
         s.xfb_pass.read_idx,
-        scale, &lightdir, &s.proj, &s.view,
+        if volume {0} else {s.stream.filled},
+        s.scale, &lightdir, &s.proj, &s.view,
     );
+    let probability = f32::from_bits(ISO_PROBABILITY.load(Ordering::Relaxed));
+    if probability > 0.0 {
+        s.surface_pass.update(&s.stream.wavefunc, probability, s.t);
+        s.surface_pass.render(
+            &s.geometry_pass,
+            s.scale, &lightdir, &s.proj, &s.view,
+        );
+    }
+    if SHOW_NODES.load(Ordering::Relaxed) {
+        s.node_pass.render(
+            &s.geometry_pass,
+            s.scale, &lightdir, &s.proj, &s.view,
+        );
+    }
     s.ssao_pass.render(
         width, height,
         &s.geometry_pass.t_gdata,
+        &s.proj,
     );
     s.blend_pass.render(
-        width, height,
         &s.geometry_pass.t_color,
         &s.ssao_pass.t_occlusion,
     );
+    if volume {
+        s.volume_pass.update(&s.stream.wavefunc, s.t);
+        s.volume_pass.render(
+            &s.blend_pass,
+            &s.stream.wavefunc,
+            &s.geometry_pass.t_gdata,
+            s.scale, &s.proj, &s.view,
+        );
+    }
+    s.blend_pass.present();
 
     s._frame = request_animation_frame(render);
 }
 
 fn setup_event_handlers(
-    window: &web_sys::Window,
     document: &web_sys::Document,
 ) -> Vec<EventListener> {
-    let resize = EventListener::new(window, "resize",
-        |_e: &web_sys::Event| {
-            let s = unsafe { STATE.as_mut().unwrap_throw() };
-            let window = web_sys::window().unwrap_throw();
-            let width = window
-                .inner_width().unwrap_throw()
-                .as_f64().unwrap_throw();
-            let height = window
-                .inner_height().unwrap_throw()
-                .as_f64().unwrap_throw();
-            let aspect = (width/height) as f32;
-            s.canvas.set_width(width as u32);
-            s.canvas.set_height(height as u32);
-            s.context.viewport(0, 0, width as i32, height as i32);
-            s.proj[(0, 0)] = s.proj[(1, 1)] / aspect;
-        },
-    );
-
     let mouseup = EventListener::new(document, "mouseup",
         |e: &web_sys::Event| {
             if e.dyn_ref::<web_sys::MouseEvent>().is_some() {
@@ -516,5 +1331,5 @@ fn setup_event_handlers(
         },
     );
 
-    return vec![resize, mouseup, mousedown, mousemove];
+    return vec![mouseup, mousedown, mousemove];
 }
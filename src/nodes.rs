@@ -0,0 +1,79 @@
+use std::f32::consts::PI;
+use nalgebra::Vector3;
+use crate::icosphere::IcoSphere;
+use crate::wavefunc::Nodes;
+
+type Vec3 = Vector3<f32>;
+
+// Icosphere subdivisions of the radial node spheres
+const SPHERE_LOD: usize = 3;
+// Segments of the cone and plane triangle fans
+const SEGMENTS: usize = 96;
+
+/// Triangle list of the nodal surfaces, with cones and planes cut off at
+/// the extent of the state
+pub struct NodeMesh {
+    pub n_vert: usize,
+    vertices: Vec<f32>,
+    normals: Vec<f32>,
+}
+
+impl NodeMesh {
+    pub fn new(nodes: &Nodes, extent: f32) -> Self {
+        let mut mesh = Self {n_vert: 0, vertices: Vec::new(), normals: Vec::new()};
+        if !nodes.radial.is_empty() {
+            let sphere = IcoSphere::new(SPHERE_LOD);
+            let (vertices, normals) = (sphere.vertex_buf(), sphere.normal_buf());
+            for r in &nodes.radial {
+                mesh.vertices.extend(vertices.iter().map(|v| v*r));
+                mesh.normals.extend_from_slice(&normals);
+            }
+        }
+        for &theta in &nodes.cones {
+            let (sin, cos) = theta.sin_cos();
+            mesh.fan(
+                |phi| Vec3::new(sin*phi.cos(), sin*phi.sin(), cos)*extent,
+                |phi| Vec3::new(cos*phi.cos(), cos*phi.sin(), -sin),
+            );
+        }
+        for &phi in &nodes.planes {
+            let (sin, cos) = phi.sin_cos();
+            let normal = Vec3::new(-sin, cos, 0.0);
+            mesh.fan(
+                |theta| Vec3::new(theta.sin()*cos, theta.sin()*sin, theta.cos())*extent,
+                |_| normal,
+            );
+        }
+        mesh.n_vert = mesh.vertices.len()/3;
+
+        mesh
+    }
+
+    /// Triangle fan from the origin to the closed curve rim(t), t in [0, 2π)
+    fn fan(
+        &mut self,
+        rim: impl Fn(f32) -> Vec3,
+        normal: impl Fn(f32) -> Vec3,
+    ) {
+        let t = |i: usize| 2.0*PI*i as f32/SEGMENTS as f32;
+        for i in 0..SEGMENTS {
+            let (a, b) = (t(i), t(i+1));
+            for (v, n) in [
+                (Vec3::zeros(), normal(0.5*(a+b))),
+                (rim(a), normal(a)),
+                (rim(b), normal(b)),
+            ] {
+                self.vertices.extend_from_slice(v.as_slice());
+                self.normals.extend_from_slice(n.as_slice());
+            }
+        }
+    }
+
+    pub fn vertex_buf(&self) -> &[f32] {
+        &self.vertices
+    }
+
+    pub fn normal_buf(&self) -> &[f32] {
+        &self.normals
+    }
+}
@@ -0,0 +1,93 @@
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+#[cfg(feature = "threads")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "threads")]
+use wasm_bindgen::prelude::*;
+
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
+// Web workers only join the pool after initThreadPool resolves
+#[cfg(feature = "threads")]
+static POOL_READY: AtomicBool = AtomicBool::new(!cfg!(target_arch = "wasm32"));
+
+/// Called once the thread pool is up, sampling before that stays on the main thread
+#[cfg(feature = "threads")]
+#[wasm_bindgen(js_name = enableThreads)]
+pub fn enable_threads() {
+    POOL_READY.store(true, Ordering::Release);
+}
+
+#[cfg(feature = "threads")]
+pub fn pool_ready() -> bool {
+    POOL_READY.load(Ordering::Acquire)
+}
+
+/// Applies f to consecutive chunks of len elements of out along with their
+/// index, on the thread pool with the threads feature. Chunks are fixed in
+/// size, so results only depend on the chunk index and not on the thread count.
+/// Waiting for the pool blocks, which the browser main thread cannot do.
+pub fn for_each_chunk<T: Send>(
+    out: &mut [T],
+    len: usize,
+    f: impl Fn(usize, &mut [T]) + Sync + Send,
+) {
+    #[cfg(feature = "threads")]
+    if pool_ready() {
+        out.par_chunks_mut(len).enumerate().for_each(|(i, c)| f(i, c));
+        return;
+    }
+    out.chunks_mut(len).enumerate().for_each(|(i, c)| f(i, c));
+}
+
+#[cfg(all(test, feature = "threads"))]
+mod tests {
+    use nalgebra::Complex;
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+    use crate::sampler::{self, Direct, Regions};
+    use crate::sequence::Sobol;
+    use crate::wavefunc::{Psi, Basis, QuantumNumbers, Superposition};
+
+    const POINTS: usize = 50000;
+
+    fn psi(n: u32, l: u32, m: i32) -> Psi {
+        Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(Basis::Real).build()
+    }
+
+    /// Densities and samples of one run on a pool of the given size
+    fn run(threads: usize) -> Vec<Vec<f32>> {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        pool.install(|| {
+            let direct = Direct::new(&psi(4, 2, 1));
+            let wavefunc = Superposition::new(vec![
+                (Complex::new(0.6, 0.0), psi(3, 1, 1)),
+                (Complex::new(0.0, 0.8), psi(3, 2, -1)),
+            ]).unwrap();
+
+            let mut rng = SmallRng::seed_from_u64(42);
+            let mut points = vec![0.0; POINTS*3];
+            direct.sample(&mut Sobol::new(&mut rng), &mut points);
+            let mut pseudo = vec![0.0; POINTS*3];
+            direct.sample(&mut rng, &mut pseudo);
+            let [x, y, z] = [0, 1, 2].map(|j| points.iter().skip(j).step_by(3).copied().collect::<Vec<_>>());
+            let density = sampler::density(&wavefunc, 0.7, &x, &y, &z);
+            let mut regions = vec![0.0; 3000];
+            Regions::symmetric(&wavefunc).sample(&wavefunc, 0.0, &mut Sobol::new(&mut rng), &mut regions);
+
+            vec![points, pseudo, density, regions]
+        })
+    }
+
+    #[test]
+    fn independent_of_thread_count() {
+        let reference = run(1);
+        for threads in [2, 3, 8] {
+            let out = run(threads);
+            for (a, b) in reference.iter().zip(&out) {
+                assert!(a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.to_bits() == b.to_bits()), "{} threads", threads);
+            }
+        }
+    }
+}
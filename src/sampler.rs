@@ -0,0 +1,900 @@
+use nalgebra::Complex;
+use std::cell::Cell;
+use std::f32::consts::PI;
+use std::mem;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wasm_bindgen::prelude::*;
+use crate::parallel;
+use crate::sequence::{Candidates, Split};
+use crate::wavefunc::{Psi, Basis, Superposition, Symmetry};
+
+const CANDIDATE_BATCH: usize = 64;
+const SAMPLE_CHUNK: usize = 4096;
+const CDF_BINS: usize = 4096;
+const PILOT_SAMPLES: usize = 512;
+// Grid points per dimension of a region, probed for its density bound
+const PROBE_STEPS: usize = 8;
+// Factor on the largest density found in a region
+const BOUND_MARGIN: f32 = 1.5;
+const AXIAL_IMAGES: usize = 8;
+const GOLDEN: f32 = 0.618034;
+
+// Points per call of Superposition::eval_into, and per work item of the thread pool
+static EVAL_BATCH: AtomicUsize = AtomicUsize::new(1024);
+
+thread_local! {
+    static PSI: Cell<Vec<Complex<f32>>> = Cell::default();
+}
+
+/// Sets the number of points evaluated at once, to tune for the device
+#[wasm_bindgen(js_name = setEvalBatch)]
+pub fn set_eval_batch(n: usize) {
+    EVAL_BATCH.store(n.max(1), Ordering::Relaxed);
+}
+
+/// |ψ(x, t)|² for points given as separate x, y, z slices
+pub fn density(
+    wavefunc: &Superposition,
+    t: f32,
+    x: &[f32],
+    y: &[f32],
+    z: &[f32],
+) -> Vec<f32> {
+    let batch = EVAL_BATCH.load(Ordering::Relaxed);
+    let mut out = vec![0.0; x.len()];
+    parallel::for_each_chunk(&mut out, batch, |c, p| {
+        let i = c*batch..c*batch+p.len();
+        let mut psi = PSI.take();
+        psi.resize(p.len(), Complex::default());
+        wavefunc.eval_into(&x[i.clone()], &y[i.clone()], &z[i], t, &mut psi);
+        for (o, v) in p.iter_mut().zip(&psi) {
+            *o = v.norm_sqr();
+        }
+        PSI.set(psi);
+    });
+    out
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MetropolisConfig {
+    /// Half-width of the uniform proposal cube
+    pub step: f32,
+    /// Discarded steps per chain before the first sample
+    pub burn_in: usize,
+    /// Steps per chain between recorded samples
+    pub thin: usize,
+    pub chains: usize,
+}
+
+impl MetropolisConfig {
+    /// Defaults scaled to the size of the wavefunction
+    pub fn new(wavefunc: &Superposition) -> Self {
+        Self {
+            step: 0.3*wavefunc.extent(),
+            burn_in: 500,
+            thin: 8,
+            chains: 256,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+    /// Fraction of accepted proposals per chain or region
+    pub acceptance: Vec<f32>,
+}
+
+impl Diagnostics {
+    pub fn mean_acceptance(&self) -> f32 {
+        self.acceptance.iter().sum::<f32>()/self.acceptance.len().max(1) as f32
+    }
+}
+
+/// Metropolis-Hastings sampler of |ψ|² with independent chains,
+/// which persist between calls so that only the first call burns in.
+/// Consecutive candidates go to different chains, so this expects
+/// pseudo-random rather than quasi-random candidates.
+pub struct Metropolis {
+    config: MetropolisConfig,
+    pos: [Vec<f32>; 3],
+    burned_in: bool,
+}
+
+impl Metropolis {
+    pub fn new(
+        config: MetropolisConfig,
+        wavefunc: &Superposition,
+        gen: &mut impl Candidates,
+    ) -> Self {
+        let bound = 0.5*wavefunc.extent();
+        let mut pos = [(); 3].map(|_| Vec::with_capacity(config.chains));
+        for _ in 0..config.chains {
+            let u = gen.point::<3>();
+            for j in 0..3 {
+                pos[j].push((2.0*u[j]-1.0)*bound);
+            }
+        }
+
+        Self {config, pos, burned_in: false}
+    }
+
+    /// Fills out with x, y, z triplets distributed according to |ψ(x, t)|²
+    pub fn sample(
+        &mut self,
+        wavefunc: &Superposition,
+        t: f32,
+        gen: &mut impl Candidates,
+        out: &mut [f32],
+    ) -> Diagnostics {
+        let MetropolisConfig {step, burn_in, thin, chains} = self.config;
+        let mut accepted = vec![0usize; chains];
+        let mut proposed = 0;
+        let [x, y, z] = &mut self.pos;
+        let mut p = density(wavefunc, t, x, y, z);
+
+        let num = out.len()/3;
+        let skip = if self.burned_in {0} else {burn_in};
+        let steps = skip+thin.max(1)*num.div_ceil(chains);
+        let mut samples = 0;
+        for s in 1..=steps {
+            let mut prop = [(); 3].map(|_| Vec::with_capacity(chains));
+            let mut accept = Vec::with_capacity(chains);
+            for c in 0..chains {
+                let u = gen.point::<4>();
+                for (j, v) in [&*x, &*y, &*z].into_iter().enumerate() {
+                    prop[j].push(v[c]+(2.0*u[j]-1.0)*step);
+                }
+                accept.push(u[3]);
+            }
+            let [px, py, pz] = &prop;
+            let q = density(wavefunc, t, px, py, pz);
+            for c in 0..chains {
+                if accept[c]*p[c] < q[c] {
+                    x[c] = px[c];
+                    y[c] = py[c];
+                    z[c] = pz[c];
+                    p[c] = q[c];
+                    accepted[c] += 1;
+                }
+            }
+            proposed += 1;
+
+            if s > skip && (s-skip)%thin.max(1) == 0 {
+                for c in 0..chains {
+                    if samples == num { break }
+                    out[samples*3..samples*3+3].copy_from_slice(&[x[c], y[c], z[c]]);
+                    samples += 1;
+                }
+            }
+        }
+        self.burned_in = true;
+
+        Diagnostics {
+            acceptance: accepted.iter().map(|a| *a as f32/proposed as f32).collect(),
+        }
+    }
+}
+
+/// Tabulated cumulative distribution of a density on [a, b]
+pub struct Cdf {
+    grid: Vec<f32>,
+    cdf: Vec<f32>,
+}
+
+impl Cdf {
+    pub fn new(a: f32, b: f32, pdf: impl Fn(f32) -> f32) -> Self {
+        let h = (b-a)/CDF_BINS as f32;
+        let grid: Vec<f32> = (0..=CDF_BINS).map(|i| a+h*i as f32).collect();
+        let vals: Vec<f32> = grid.iter().map(|x| pdf(*x)).collect();
+        let mut cdf = Vec::with_capacity(grid.len());
+        let mut acc = 0.0f64;
+        cdf.push(0.0);
+        for i in 1..grid.len() {
+            acc += 0.5*(vals[i-1]+vals[i]) as f64*h as f64;
+            cdf.push(acc as f32);
+        }
+        let total = acc as f32;
+        cdf.iter_mut().for_each(|c| *c /= total);
+
+        Self {grid, cdf}
+    }
+
+    pub fn eval(&self, x: f32) -> f32 {
+        let i = self.grid.partition_point(|g| *g <= x).clamp(1, self.grid.len()-1);
+        let (x_0, x_1) = (self.grid[i-1], self.grid[i]);
+        let w = ((x-x_0)/(x_1-x_0)).clamp(0.0, 1.0);
+        self.cdf[i-1]+w*(self.cdf[i]-self.cdf[i-1])
+    }
+
+    /// Inverse transform of u ∈ [0, 1), linear within bins
+    pub fn invert(&self, u: f32) -> f32 {
+        let i = self.cdf.partition_point(|c| *c <= u).clamp(1, self.cdf.len()-1);
+        let (c_0, c_1) = (self.cdf[i-1], self.cdf[i]);
+        let w = if c_1 > c_0 {(u-c_0)/(c_1-c_0)} else {0.0};
+        self.grid[i-1]+w*(self.grid[i]-self.grid[i-1])
+    }
+}
+
+/// Exact sampler of separable eigenstates |ψ|² = r²R² · Θ² sinθ · |Φ|²,
+/// drawing r, cos(θ) and φ independently from their marginals
+pub struct Direct {
+    pub radial: Cdf,
+    pub polar: Cdf,
+    /// None for the uniform φ distribution of complex eigenstates
+    pub azimuthal: Option<Cdf>,
+}
+
+impl Direct {
+    pub fn new(psi: &Psi) -> Self {
+        let m = psi.quantum_numbers().m();
+        let radial = Cdf::new(0.0, 2.0*psi.extent(), |r| (r*psi.radial(r)).powi(2));
+        // Substituting cos(θ) absorbs the sinθ Jacobian
+        let polar = Cdf::new(-1.0, 1.0, |c| psi.polar(c).powi(2));
+        let azimuthal = match psi.basis() {
+            Basis::Real if m > 0 => Some(Cdf::new(0.0, 2.0*PI, |p| (m as f32*p).cos().powi(2))),
+            Basis::Real if m < 0 => Some(Cdf::new(0.0, 2.0*PI, |p| (m as f32*p).sin().powi(2))),
+            _ => None,
+        };
+
+        Self {radial, polar, azimuthal}
+    }
+
+    /// Fills out with x, y, z triplets distributed according to |ψ|²
+    pub fn sample(
+        &self,
+        gen: &mut impl Split,
+        out: &mut [f32],
+    ) {
+        let base = &*gen;
+        parallel::for_each_chunk(out, SAMPLE_CHUNK*3, |c, chunk| {
+            let mut gen = base.split(c, SAMPLE_CHUNK);
+            for p in chunk.chunks_exact_mut(3) {
+                let [u, v, w] = gen.point::<3>();
+                let r = self.radial.invert(u);
+                let cos_theta = self.polar.invert(v);
+                let phi = match &self.azimuthal {
+                    Some(cdf) => cdf.invert(w),
+                    None => w*2.0*PI,
+                };
+                let sin_theta = (1.0-cos_theta*cos_theta).max(0.0).sqrt();
+                p.copy_from_slice(&[
+                    r*sin_theta*phi.cos(),
+                    r*sin_theta*phi.sin(),
+                    r*cos_theta,
+                ]);
+            }
+        });
+        gen.skip(out.len()/3);
+    }
+}
+
+/// Importance sampler over regions bounded by radial shells and polar bands.
+/// The shell and band count grows with n and l, the probability mass of each
+/// region is estimated from pilot samples, its density bound from the pilot
+/// and a grid probe, and each region is then filled with its proportional
+/// quota by rejection against that bound. A candidate above the bound raises
+/// it and restarts the region if none of its samples were returned yet, later
+/// the bound stays fixed so that the sample set remains consistent.
+/// With a symmetry, only its fundamental domain (a φ-wedge, z ≥ 0) is sampled
+/// and every accepted sample is replicated under the symmetry group.
+/// Masses are estimated on the first call, later calls extend the sample set
+/// so that every prefix keeps the proportional quotas, and images which did
+/// not fit into one call are returned by the next.
+pub struct Regions {
+    shells: Vec<f32>,
+    bands: Vec<f32>,
+    symmetry: Symmetry,
+    wedge: f32,
+    mass: Vec<f32>,
+    bound: Vec<f32>,
+    drawn: Vec<usize>,
+    replicated: usize,
+    pending: Vec<f32>,
+}
+
+impl Regions {
+    pub fn new(wavefunc: &Superposition) -> Self {
+        Self::with_symmetry(wavefunc, Symmetry::TRIVIAL)
+    }
+
+    /// Samples the fundamental domain of the symmetry of |ψ|²
+    pub fn symmetric(wavefunc: &Superposition) -> Self {
+        Self::with_symmetry(wavefunc, wavefunc.symmetry())
+    }
+
+    fn with_symmetry(wavefunc: &Superposition, symmetry: Symmetry) -> Self {
+        let (n, l) = wavefunc.terms().iter()
+            .map(|(_, psi)| psi.quantum_numbers())
+            .fold((1, 0), |(n, l), qn| (n.max(qn.n()), l.max(qn.l())));
+        let r_max = 2.0*wavefunc.extent();
+        // Quadratic spacing, finer towards the nucleus
+        let n_shells = 4*n as usize;
+        let shells = (0..=n_shells)
+            .map(|i| r_max*(i as f32/n_shells as f32).powi(2))
+            .collect();
+        let (c_0, n_bands) = match symmetry.horizontal {
+            true => (0.0, l as usize+1),
+            false => (-1.0, 2*(l as usize+1)),
+        };
+        let bands = (0..=n_bands)
+            .map(|i| c_0+(1.0-c_0)*i as f32/n_bands as f32)
+            .collect();
+        let wedge = match symmetry {
+            Symmetry {rotation: 0, ..} => 2.0*PI,
+            Symmetry {rotation, vertical: true, ..} => PI/rotation as f32,
+            Symmetry {rotation, ..} => 2.0*PI/rotation as f32,
+        };
+
+        Self {
+            shells, bands, symmetry, wedge,
+            mass: Vec::new(),
+            bound: Vec::new(),
+            drawn: Vec::new(),
+            replicated: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Number of images of each sample from the fundamental domain
+    pub fn order(&self) -> usize {
+        let Symmetry {rotation, vertical, horizontal} = self.symmetry;
+        let rotations = if rotation == 0 { AXIAL_IMAGES } else { rotation as usize };
+        rotations*(1+vertical as usize)*(1+horizontal as usize)
+    }
+
+    /// Point of a region at u, v, w in [0, 1], uniform in volume
+    fn point(&self, region: usize, u: f32, v: f32, w: f32) -> [f32; 3] {
+        let nb = self.bands.len()-1;
+        let (i, j) = (region/nb, region%nb);
+        let (r_0, r_1) = (self.shells[i].powi(3), self.shells[i+1].powi(3));
+        let (c_0, c_1) = (self.bands[j], self.bands[j+1]);
+        let r = (r_0+u*(r_1-r_0)).cbrt();
+        let c = c_0+v*(c_1-c_0);
+        let phi = w*self.wedge;
+        let s = (1.0-c*c).max(0.0).sqrt();
+        [r*s*phi.cos(), r*s*phi.sin(), r*c]
+    }
+
+    fn candidates(
+        &self,
+        region: usize,
+        gen: &mut impl Candidates,
+        num: usize,
+    ) -> [Vec<f32>; 4] {
+        let mut pos = [(); 4].map(|_| Vec::with_capacity(num));
+        for _ in 0..num {
+            let [u, v, w, a] = gen.point::<4>();
+            let p = self.point(region, u, v, w);
+            for j in 0..3 {
+                pos[j].push(p[j]);
+            }
+            // Acceptance variate, part of the same point
+            pos[3].push(a);
+        }
+        pos
+    }
+
+    /// Regular grid over a region including its boundary, where the density
+    /// of s states peaks at the nucleus
+    fn probe(&self, region: usize) -> [Vec<f32>; 3] {
+        let g = |k: usize| k as f32/(PROBE_STEPS-1) as f32;
+        let mut pos = [(); 3].map(|_| Vec::with_capacity(PROBE_STEPS.pow(3)));
+        for i in 0..PROBE_STEPS.pow(3) {
+            let (u, v, w) = (i/PROBE_STEPS.pow(2), i/PROBE_STEPS%PROBE_STEPS, i%PROBE_STEPS);
+            let p = self.point(region, g(u), g(v), g(w));
+            for j in 0..3 {
+                pos[j].push(p[j]);
+            }
+        }
+        pos
+    }
+
+    fn volume(&self, region: usize) -> f32 {
+        let nb = self.bands.len()-1;
+        let (i, j) = (region/nb, region%nb);
+        (self.shells[i+1].powi(3)-self.shells[i].powi(3))/3.0
+            *(self.bands[j+1]-self.bands[j])*self.wedge
+    }
+
+    /// Appends the images of the i-th sample p under the symmetry group, axial
+    /// symmetry is represented by evenly spaced rotations with an offset
+    /// following the golden ratio sequence
+    fn replicate(&self, i: usize, p: &[f32], out: &mut Vec<f32>) {
+        let Symmetry {rotation, vertical, horizontal} = self.symmetry;
+        let (k, offset) = match rotation {
+            0 => (AXIAL_IMAGES, (i as f32*GOLDEN).fract()*2.0*PI),
+            k => (k as usize, 0.0),
+        };
+        let mirror: &[f32] = if vertical { &[1.0, -1.0] } else { &[1.0] };
+        let flip: &[f32] = if horizontal { &[1.0, -1.0] } else { &[1.0] };
+        for j in 0..k {
+            let (s, c) = (offset+2.0*PI*j as f32/k as f32).sin_cos();
+            for m in mirror {
+                let y = m*p[1];
+                for f in flip {
+                    out.extend([c*p[0]-s*y, s*p[0]+c*y, f*p[2]]);
+                }
+            }
+        }
+    }
+
+    /// Fills out with x, y, z triplets distributed according to |ψ(x, t)|²
+    pub fn sample(
+        &mut self,
+        wavefunc: &Superposition,
+        t: f32,
+        gen: &mut impl Candidates,
+        out: &mut [f32],
+    ) -> Diagnostics {
+        let order = self.order();
+        if order == 1 {
+            return self.sample_domain(wavefunc, t, gen, out);
+        }
+        let num = out.len()/3;
+        let missing = num.saturating_sub(self.pending.len()/3);
+        let mut domain = vec![0.0; missing.div_ceil(order)*3];
+        let diagnostics = self.sample_domain(wavefunc, t, gen, &mut domain);
+        let mut images = mem::take(&mut self.pending);
+        for p in domain.chunks_exact(3) {
+            self.replicate(self.replicated, p, &mut images);
+            self.replicated += 1;
+        }
+        out[..num*3].copy_from_slice(&images[..num*3]);
+        self.pending = images.split_off(num*3);
+
+        diagnostics
+    }
+
+    fn estimate(
+        &mut self,
+        wavefunc: &Superposition,
+        t: f32,
+        gen: &mut impl Candidates,
+    ) {
+        let n_regions = (self.shells.len()-1)*(self.bands.len()-1);
+        for k in 0..n_regions {
+            let [x, y, z, _] = self.candidates(k, gen, PILOT_SAMPLES);
+            let p = density(wavefunc, t, &x, &y, &z);
+            self.mass.push(p.iter().sum::<f32>()/PILOT_SAMPLES as f32*self.volume(k));
+            let [x, y, z] = self.probe(k);
+            let q = density(wavefunc, t, &x, &y, &z);
+            // Safety margin, the sampled maximum underestimates the true one
+            let max = p.iter().chain(&q).copied().fold(0.0, f32::max);
+            self.bound.push(BOUND_MARGIN*max);
+        }
+        self.drawn = vec![0; n_regions];
+    }
+
+    /// Per-region counts which bring the regions drawn so far to the quotas of
+    /// num more samples, by cumulative rounding so that they add up exactly
+    fn quotas(&self, num: usize) -> Vec<usize> {
+        let total = self.mass.iter().sum::<f32>();
+        let goal = self.drawn.iter().sum::<usize>()+num;
+        let mut acc = 0.0;
+        let mut prev = 0;
+        let mut quotas = Vec::with_capacity(self.mass.len());
+        for (m, drawn) in self.mass.iter().zip(&self.drawn) {
+            acc += m/total;
+            let next = ((acc*goal as f32).round() as usize).min(goal);
+            quotas.push((next-prev).saturating_sub(*drawn));
+            prev = next;
+        }
+        // Rounding of earlier calls may leave a few samples to move
+        let heaviest = (0..self.mass.len())
+            .max_by(|&i, &j| self.mass[i].total_cmp(&self.mass[j]))
+            .unwrap_or(0);
+        let mut sum = quotas.iter().sum::<usize>();
+        while sum < num {
+            quotas[heaviest] += 1;
+            sum += 1;
+        }
+        while sum > num {
+            let k = (0..quotas.len()).max_by_key(|&k| quotas[k]).unwrap_or(0);
+            quotas[k] -= 1;
+            sum -= 1;
+        }
+        quotas
+    }
+
+    fn sample_domain(
+        &mut self,
+        wavefunc: &Superposition,
+        t: f32,
+        gen: &mut impl Candidates,
+        out: &mut [f32],
+    ) -> Diagnostics {
+        if self.mass.is_empty() {
+            self.estimate(wavefunc, t, gen);
+        }
+        let n_regions = self.mass.len();
+        let quotas = self.quotas(out.len()/3);
+
+        let mut acceptance = vec![0.0; n_regions];
+        let mut samples = 0;
+        for k in 0..n_regions {
+            let quota = quotas[k];
+            let mut proposed = 0;
+            let mut filled = 0;
+            while filled < quota {
+                let [x, y, z, a] = self.candidates(k, gen, CANDIDATE_BATCH);
+                let p = density(wavefunc, t, &x, &y, &z);
+                for i in 0..CANDIDATE_BATCH {
+                    if filled == quota { break }
+                    proposed += 1;
+                    // Samples accepted against a bound exceeded by a candidate would
+                    // be biased. Until the region has returned samples it starts over
+                    // with a raised bound, after that the density is clipped to it.
+                    if p[i] > self.bound[k] && self.drawn[k] == 0 {
+                        self.bound[k] = BOUND_MARGIN*p[i];
+                        samples -= filled;
+                        filled = 0;
+                        proposed = 0;
+                        continue;
+                    }
+                    if a[i]*self.bound[k] < p[i] {
+                        out[samples*3..samples*3+3].copy_from_slice(&[x[i], y[i], z[i]]);
+                        samples += 1;
+                        filled += 1;
+                    }
+                }
+            }
+            if proposed > 0 {
+                acceptance[k] = filled as f32/proposed as f32;
+            }
+            self.drawn[k] += filled;
+        }
+
+        Diagnostics {acceptance}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+    use crate::sequence::Sobol;
+    use crate::wavefunc::QuantumNumbers;
+
+    const SAMPLES: usize = 20000;
+    // Kolmogorov-Smirnov critical value at a significance of 0.1%
+    const KS_CRITICAL: f32 = 1.95;
+
+    /// Largest distance between the empirical distribution of x and cdf
+    fn ks(mut x: Vec<f32>, cdf: impl Fn(f32) -> f32) -> f32 {
+        x.sort_by(f32::total_cmp);
+        let n = x.len() as f32;
+        x.iter().enumerate()
+            .map(|(i, &x)| {
+                let f = cdf(x);
+                (f-i as f32/n).max((i+1) as f32/n-f)
+            })
+            .fold(0.0, f32::max)
+    }
+
+    /// r, cos(θ) and φ in [0, 2π) of x, y, z triplets
+    fn spherical(points: &[f32]) -> [Vec<f32>; 3] {
+        let mut out = [(); 3].map(|_| Vec::with_capacity(points.len()/3));
+        for p in points.chunks_exact(3) {
+            let r = (p[0]*p[0]+p[1]*p[1]+p[2]*p[2]).sqrt();
+            out[0].push(r);
+            out[1].push(p[2]/r);
+            out[2].push(p[1].atan2(p[0]).rem_euclid(2.0*PI));
+        }
+        out
+    }
+
+    /// Distribution of the density Σ cₖ·rᵏ·e^{-βr} on [0, ∞), from
+    /// ∫₀ˣ rᵏ·e^{-βr} dr = k!/β^{k+1}·(1 - e^{-βx}·Σ_{j≤k} (βx)ʲ/j!)
+    fn gamma_cdf(coeffs: &'static [f64], beta: f64) -> impl Fn(f32) -> f32 {
+        let moment = move |k: usize| (1..=k).fold(1.0, |f, j| f*j as f64)/beta.powi(k as i32+1);
+        let total = coeffs.iter().enumerate().map(|(k, c)| c*moment(k)).sum::<f64>();
+        move |x| {
+            let y = beta*x as f64;
+            coeffs.iter().enumerate()
+                .map(|(k, c)| {
+                    let (mut term, mut sum) = (1.0, 1.0);
+                    for j in 1..=k {
+                        term *= y/j as f64;
+                        sum += term;
+                    }
+                    c*moment(k)*(1.0-(-y).exp()*sum)
+                })
+                .sum::<f64>() as f32/total as f32
+        }
+    }
+
+    /// Distribution of the density Σ aₖ·cᵏ on [-1, 1]
+    fn poly_cdf(coeffs: &'static [f64]) -> impl Fn(f32) -> f32 {
+        let integral = |c: f64| coeffs.iter().enumerate()
+            .map(|(k, a)| a*c.powi(k as i32+1)/(k+1) as f64)
+            .sum::<f64>();
+        let (lo, hi) = (integral(-1.0), integral(1.0));
+        move |c| ((integral(c as f64)-lo)/(hi-lo)) as f32
+    }
+
+    fn psi(n: u32, l: u32, m: i32, basis: Basis) -> Psi {
+        Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build()
+    }
+
+    #[test]
+    fn cdf_closed_form() {
+        // 1s: P(r < x) = 1-e^{-2x}(1+2x+2x²), 2p_z: P(cos θ < x) = (x³+1)/2
+        let radial = Cdf::new(0.0, 20.0, |r| r*r*(-2.0*r).exp());
+        let polar = Cdf::new(-1.0, 1.0, |c| c*c);
+        for i in 0..=50 {
+            let x = 0.1*i as f32;
+            let expected = 1.0-(-2.0*x).exp()*(1.0+2.0*x+2.0*x*x);
+            assert!((radial.eval(x)-expected).abs() < 1e-5, "{} at {}", radial.eval(x), x);
+            let c = 0.04*i as f32-1.0;
+            assert!((polar.eval(c)-0.5*(c*c*c+1.0)).abs() < 1e-5);
+            assert!((radial.eval(radial.invert(0.02*i as f32))-0.02*i as f32).abs() < 1e-5);
+        }
+    }
+
+    /// Radial marginal, and polar marginal of single states, of Regions samples
+    /// from quasi-random candidates as in the renderer
+    #[test]
+    fn regions_marginals() {
+        let bound = KS_CRITICAL/(SAMPLES as f32).sqrt();
+        let cases = [
+            vec![(1.0, psi(1, 0, 0, Basis::Complex))],
+            vec![(1.0, psi(3, 1, 1, Basis::Real))],
+            vec![(1.0, psi(4, 2, 0, Basis::Complex))],
+            vec![(0.6, psi(2, 0, 0, Basis::Complex)), (0.8, psi(2, 1, 0, Basis::Complex))],
+            vec![(0.8, psi(3, 0, 0, Basis::Complex)), (0.6, psi(3, 2, -2, Basis::Real))],
+        ];
+        for terms in cases {
+            let (w, states): (Vec<f32>, Vec<Psi>) = terms.into_iter().unzip();
+            let single = w.len() == 1;
+            let radial_cdfs: Vec<_> = states.iter()
+                .map(|psi| Cdf::new(0.0, 3.0*psi.extent(), |r| psi.radial_density(r)))
+                .collect();
+            let polar = Cdf::new(-1.0, 1.0, |c| states[0].angular_density(c));
+            let wavefunc = Superposition::new(
+                w.iter().map(|c| Complex::from(*c)).zip(states).collect(),
+            ).unwrap();
+
+            let mut out = vec![0.0; SAMPLES*3];
+            let mut gen = Sobol::new(&mut SmallRng::seed_from_u64(11));
+            Regions::new(&wavefunc).sample(&wavefunc, 0.0, &mut gen, &mut out);
+            let [r, cos_theta, _] = spherical(&out);
+
+            let label = wavefunc.label();
+            let d_r = ks(r, |x| w.iter().zip(&radial_cdfs).map(|(c, cdf)| c*c*cdf.eval(x)).sum());
+            assert!(d_r < bound, "{}: r {} > {}", label, d_r, bound);
+            if single {
+                let d_theta = ks(cos_theta, |x| polar.eval(x));
+                assert!(d_theta < bound, "{}: cos θ {} > {}", label, d_theta, bound);
+            }
+        }
+    }
+
+    /// No density within a region exceeds its bound, which would bias the rejection
+    #[test]
+    fn regions_bounds() {
+        for psi in [
+            psi(1, 0, 0, Basis::Complex),
+            psi(2, 1, 1, Basis::Real),
+            psi(4, 0, 0, Basis::Complex),
+            psi(5, 3, -2, Basis::Real),
+            psi(6, 1, 0, Basis::Complex),
+        ] {
+            let wavefunc = Superposition::new(vec![(Complex::from(1.0), psi)]).unwrap();
+            let mut sampler = Regions::new(&wavefunc);
+            let mut gen = SmallRng::seed_from_u64(3);
+            sampler.sample(&wavefunc, 0.0, &mut gen, &mut [0.0; 3]);
+            for k in 0..sampler.bound.len() {
+                let [x, y, z, _] = sampler.candidates(k, &mut gen, 4096);
+                let max = density(&wavefunc, 0.0, &x, &y, &z).into_iter().fold(0.0, f32::max);
+                assert!(max <= sampler.bound[k], "{} region {}: {} > {}", wavefunc.label(), k, max, sampler.bound[k]);
+            }
+        }
+    }
+
+    /// Bounds that are too low restart their regions on the first call, after which
+    /// the radial marginal of 3p_x, r⁴(6-r)²e^{-2r/3}, and the acceptance rates match
+    /// rejection against the raised bounds. Once a region has returned samples its
+    /// bound stays fixed.
+    #[test]
+    fn regions_restart() {
+        let wavefunc = Superposition::new(vec![(Complex::from(1.0), psi(3, 1, 1, Basis::Real))]).unwrap();
+        let mut sampler = Regions::new(&wavefunc);
+        let mut gen = Sobol::new(&mut SmallRng::seed_from_u64(23));
+        sampler.sample(&wavefunc, 0.0, &mut gen, &mut []);
+        sampler.bound.iter_mut().for_each(|b| *b *= 0.65);
+        let mut out = vec![0.0; SAMPLES*3];
+        let diagnostics = sampler.sample(&wavefunc, 0.0, &mut gen, &mut out);
+
+        let bound = KS_CRITICAL/(SAMPLES as f32).sqrt();
+        let [r, ..] = spherical(&out);
+        let d_r = ks(r, gamma_cdf(&[0.0, 0.0, 0.0, 0.0, 36.0, -12.0, 1.0], 2.0/3.0));
+        assert!(d_r < bound, "r {} > {}", d_r, bound);
+
+        // The deviation is far below sigma with quasi-random candidates, while
+        // proposals counted before a restart lower the rate by several sigma
+        let mut rng = SmallRng::seed_from_u64(29);
+        for k in (0..sampler.bound.len()).filter(|&k| sampler.drawn[k] >= 200) {
+            let [x, y, z, _] = sampler.candidates(k, &mut rng, 4096);
+            let b = sampler.bound[k];
+            let expected = density(&wavefunc, 0.0, &x, &y, &z).iter().map(|p| p.min(b)/b).sum::<f32>()/4096.0;
+            let a = diagnostics.acceptance[k];
+            let sigma = a*((1.0-a)/sampler.drawn[k] as f32).sqrt()+(expected*(1.0-expected)/4096.0).sqrt();
+            assert!((a-expected).abs() < 2.0*sigma, "region {}: acceptance {} vs {}", k, a, expected);
+        }
+
+        let k = (0..sampler.bound.len()).max_by_key(|&k| sampler.drawn[k]).unwrap();
+        sampler.bound[k] *= 0.5;
+        let (bounds, drawn) = (sampler.bound.clone(), sampler.drawn.clone());
+        sampler.sample(&wavefunc, 0.0, &mut gen, &mut out);
+        for k in (0..bounds.len()).filter(|&k| drawn[k] > 0) {
+            assert_eq!(sampler.bound[k], bounds[k], "region {}", k);
+        }
+    }
+
+    /// Octant occupancy by a two-sample χ² test and ⟨r⟩ of samples replicated
+    /// under the symmetry, against samples of the whole space
+    #[test]
+    fn regions_symmetric() {
+        // χ² critical value with 7 degrees of freedom at a significance of 0.1%
+        const CHI2_CRITICAL: f32 = 24.32;
+        let cases = [
+            vec![(1.0, psi(3, 2, -2, Basis::Real))],
+            vec![(1.0, psi(4, 3, 3, Basis::Real))],
+            vec![(1.0, psi(3, 0, 0, Basis::Complex))],
+            vec![(0.6, psi(3, 1, 1, Basis::Real)), (0.8, psi(3, 2, 1, Basis::Real))],
+            vec![(0.8, psi(4, 1, 1, Basis::Complex)), (0.6, psi(4, 3, -1, Basis::Complex))],
+        ];
+        for terms in cases {
+            let wavefunc = Superposition::new(
+                terms.into_iter().map(|(c, psi)| (Complex::from(c), psi)).collect(),
+            ).unwrap();
+            let stats = |mut sampler: Regions, seed| {
+                let mut out = vec![0.0; SAMPLES*3];
+                let mut gen = Sobol::new(&mut SmallRng::seed_from_u64(seed));
+                sampler.sample(&wavefunc, 0.0, &mut gen, &mut out);
+                let mut octants = [0.0f32; 8];
+                let (mut sum, mut sum_2) = (0.0, 0.0);
+                for p in out.chunks_exact(3) {
+                    let o = (p[0] > 0.0) as usize | ((p[1] > 0.0) as usize) << 1 | ((p[2] > 0.0) as usize) << 2;
+                    octants[o] += 1.0;
+                    let r = (p[0]*p[0]+p[1]*p[1]+p[2]*p[2]).sqrt() as f64;
+                    sum += r;
+                    sum_2 += r*r;
+                }
+                // Images share their radius, leaving SAMPLES/order independent radii
+                let n = (SAMPLES/sampler.order()) as f64;
+                let mean = sum/SAMPLES as f64;
+                (octants, mean, (sum_2/SAMPLES as f64-mean*mean)/n)
+            };
+            let symmetric = Regions::symmetric(&wavefunc);
+            assert!(symmetric.order() > 1, "{}", wavefunc.label());
+            let (a, mean_a, var_a) = stats(symmetric, 5);
+            let (b, mean_b, var_b) = stats(Regions::new(&wavefunc), 6);
+
+            let label = wavefunc.label();
+            let chi2 = a.iter().zip(&b)
+                .filter(|(a, b)| *a+*b > 0.0)
+                .map(|(a, b)| (a-b).powi(2)/(a+b))
+                .sum::<f32>();
+            assert!(chi2 < CHI2_CRITICAL, "{}: χ² {} of {:?} and {:?}", label, chi2, a, b);
+            let z = (mean_a-mean_b)/(var_a+var_b).sqrt();
+            assert!(z.abs() < 4.0, "{}: ⟨r⟩ {} and {}", label, mean_a, mean_b);
+        }
+    }
+
+    /// Marginals of Direct samples against closed forms, with radial densities
+    /// r²R² as polynomials times e^{-2r/n} and polar densities as polynomials
+    /// in cos(θ), e.g. r²(2-r)²e^{-r} of 2s and (3cos²θ-1)² of 3d_z²
+    #[test]
+    fn direct_marginals() {
+        let cases = [
+            (psi(1, 0, 0, Basis::Complex), &[0.0, 0.0, 1.0][..], 2.0, &[1.0][..]),
+            (psi(2, 0, 0, Basis::Complex), &[0.0, 0.0, 4.0, -4.0, 1.0], 1.0, &[1.0]),
+            (psi(2, 1, 1, Basis::Complex), &[0.0, 0.0, 0.0, 0.0, 1.0], 1.0, &[1.0, 0.0, -1.0]),
+            (psi(3, 1, 1, Basis::Real), &[0.0, 0.0, 0.0, 0.0, 36.0, -12.0, 1.0], 2.0/3.0, &[1.0, 0.0, -1.0]),
+            (psi(3, 2, 0, Basis::Complex), &[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0], 2.0/3.0, &[1.0, 0.0, -6.0, 0.0, 9.0]),
+            (psi(3, 2, -2, Basis::Real), &[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0], 2.0/3.0, &[1.0, 0.0, -2.0, 0.0, 1.0]),
+        ];
+        let bound = KS_CRITICAL/(SAMPLES as f32).sqrt();
+        for (psi, radial, beta, polar) in cases {
+            let mut out = vec![0.0; SAMPLES*3];
+            Direct::new(&psi).sample(&mut SmallRng::seed_from_u64(7), &mut out);
+            let [r, cos_theta, phi] = spherical(&out);
+
+            let m = psi.quantum_numbers().m() as f32;
+            // ∫cos²(mφ) and ∫sin²(mφ) over [0, φ], normalized
+            let azimuthal = |p: f32| match psi.basis() {
+                Basis::Real if m > 0.0 => (p+(2.0*m*p).sin()/(2.0*m))/(2.0*PI),
+                Basis::Real if m < 0.0 => (p-(2.0*m*p).sin()/(2.0*m))/(2.0*PI),
+                _ => p/(2.0*PI),
+            };
+            let label = psi.label();
+            let d_r = ks(r, gamma_cdf(radial, beta));
+            let d_theta = ks(cos_theta, poly_cdf(polar));
+            let d_phi = ks(phi, azimuthal);
+            assert!(d_r < bound, "{}: r {} > {}", label, d_r, bound);
+            assert!(d_theta < bound, "{}: cos θ {} > {}", label, d_theta, bound);
+            assert!(d_phi < bound, "{}: φ {} > {}", label, d_phi, bound);
+        }
+    }
+
+    /// Marginals of Metropolis samples against closed forms of 2p_z, r⁴e^{-r}
+    /// and cos²θ, and 3d with m = 1, r⁶e^{-2r/3} and cos²θ·sin²θ. Thinning is
+    /// raised until the samples of a chain are close to independent.
+    #[test]
+    fn metropolis_marginals() {
+        let bound = KS_CRITICAL/(SAMPLES as f32).sqrt();
+        let cases = [
+            (psi(2, 1, 0, Basis::Complex), gamma_cdf(&[0.0, 0.0, 0.0, 0.0, 1.0], 1.0), poly_cdf(&[0.0, 0.0, 1.0])),
+            (psi(3, 2, 1, Basis::Complex), gamma_cdf(&[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0], 2.0/3.0), poly_cdf(&[0.0, 0.0, 1.0, 0.0, -1.0])),
+        ];
+        for (psi, radial, polar) in cases {
+            let wavefunc = Superposition::new(vec![(Complex::from(1.0), psi)]).unwrap();
+            let mut gen = SmallRng::seed_from_u64(13);
+            let config = MetropolisConfig {thin: 32, ..MetropolisConfig::new(&wavefunc)};
+            let mut sampler = Metropolis::new(config, &wavefunc, &mut gen);
+            let mut out = vec![0.0; SAMPLES*3];
+            sampler.sample(&wavefunc, 0.0, &mut gen, &mut out);
+            let [r, cos_theta, _] = spherical(&out);
+            let label = wavefunc.label();
+            let d_r = ks(r, radial);
+            let d_theta = ks(cos_theta, polar);
+            assert!(d_r < bound, "{}: r {} > {}", label, d_r, bound);
+            assert!(d_theta < bound, "{}: cos θ {} > {}", label, d_theta, bound);
+        }
+    }
+
+    /// The proposal step scaled to the extent keeps the mean acceptance of the
+    /// chains in a range that mixes well, also after the density has changed
+    #[test]
+    fn metropolis_acceptance() {
+        let cases = [
+            vec![(1.0, psi(1, 0, 0, Basis::Complex))],
+            vec![(1.0, psi(3, 2, 1, Basis::Complex))],
+            vec![(0.6, psi(2, 0, 0, Basis::Complex)), (0.8, psi(3, 1, 1, Basis::Real))],
+        ];
+        for terms in cases {
+            let wavefunc = Superposition::new(
+                terms.into_iter().map(|(c, psi)| (Complex::from(c), psi)).collect(),
+            ).unwrap();
+            let mut gen = SmallRng::seed_from_u64(17);
+            let config = MetropolisConfig::new(&wavefunc);
+            let mut sampler = Metropolis::new(config, &wavefunc, &mut gen);
+            for t in [0.0, 2.5] {
+                let diagnostics = sampler.sample(&wavefunc, t, &mut gen, &mut vec![0.0; 4096*3]);
+                let label = wavefunc.label();
+                let mean = diagnostics.mean_acceptance();
+                assert_eq!(diagnostics.acceptance.len(), config.chains);
+                assert!(diagnostics.acceptance.iter().all(|a| (0.0..=1.0).contains(a)), "{}", label);
+                assert!((0.2..0.7).contains(&mean), "{} at t = {}: acceptance {}", label, t, mean);
+            }
+        }
+    }
+
+    /// Recorded samples are the states of the chains after burn_in steps and
+    /// every thin steps after that, and later calls continue without burn-in
+    #[test]
+    fn metropolis_burn_in_and_thinning() {
+        let wavefunc = Superposition::new(vec![(Complex::from(1.0), psi(2, 1, 1, Basis::Real))]).unwrap();
+        let base = MetropolisConfig {chains: 2, ..MetropolisConfig::new(&wavefunc)};
+        let chain = |config: MetropolisConfig, calls: &[usize]| {
+            let mut gen = SmallRng::seed_from_u64(19);
+            let mut sampler = Metropolis::new(config, &wavefunc, &mut gen);
+            calls.iter()
+                .flat_map(|&num| {
+                    let mut out = vec![0.0; num*config.chains*3];
+                    sampler.sample(&wavefunc, 0.0, &mut gen, &mut out);
+                    out
+                })
+                .collect::<Vec<_>>()
+        };
+        let (burn_in, thin) = (5, 3);
+        let thinned = chain(MetropolisConfig {burn_in, thin, ..base}, &[4, 2]);
+        // Every state of the chains after each step
+        let states = chain(MetropolisConfig {burn_in: 0, thin: 1, ..base}, &[burn_in+thin*6]);
+        let steps = (1..=4).map(|k| burn_in+thin*k).chain((1..=2).map(|k| burn_in+thin*(4+k)));
+        let expected = steps.flat_map(|s| states[(s-1)*6..s*6].to_vec()).collect::<Vec<_>>();
+        assert_eq!(thinned, expected);
+    }
+}
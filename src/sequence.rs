@@ -0,0 +1,331 @@
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+
+/// Highest dimension supported by the quasi-random sequences
+pub const MAX_DIM: usize = 8;
+
+const PRIMES: [u32; MAX_DIM] = [2, 3, 5, 7, 11, 13, 17, 19];
+
+/// Degree, coefficients and initial direction numbers of the primitive
+/// polynomials for Sobol dimensions 2 to 8 (Joe & Kuo, new-joe-kuo-6.21201)
+const SOBOL_POLY: [(u32, u32, [u32; 5]); MAX_DIM-1] = [
+    (1, 0, [1, 0, 0, 0, 0]),
+    (2, 1, [1, 3, 0, 0, 0]),
+    (3, 1, [1, 3, 1, 0, 0]),
+    (3, 2, [1, 1, 1, 0, 0]),
+    (4, 1, [1, 1, 3, 3, 0]),
+    (4, 4, [1, 3, 5, 13, 0]),
+    (5, 2, [1, 1, 5, 5, 17]),
+];
+
+/// Source of candidate points in the unit hypercube [0, 1)^D.
+/// Quasi-random sequences assume every point drawn from one
+/// generator has the same dimension.
+pub trait Candidates {
+    fn point<const D: usize>(&mut self) -> [f32; D];
+}
+
+/// Generators that split into independent streams for chunks of consecutive
+/// points, which can be drawn in any order or on different threads
+pub trait Split: Candidates + Sized + Send + Sync {
+    /// Generator for the chunk-th run of len points after the current position
+    fn split(&self, chunk: usize, len: usize) -> Self;
+    /// Moves past n points split off before, as if they were drawn
+    fn skip(&mut self, n: usize);
+}
+
+impl Candidates for SmallRng {
+    fn point<const D: usize>(&mut self) -> [f32; D] {
+        [(); D].map(|_| self.gen())
+    }
+}
+
+impl Split for SmallRng {
+    /// Seeds each chunk from the next output, the chunks do not continue the stream
+    fn split(&self, chunk: usize, _len: usize) -> Self {
+        let base = self.clone().gen::<u64>();
+        SmallRng::seed_from_u64(base ^ (chunk as u64).wrapping_mul(0x9e3779b97f4a7c15))
+    }
+
+    fn skip(&mut self, _n: usize) {
+        self.gen::<u64>();
+    }
+}
+
+fn to_unit(x: u32) -> f32 {
+    (x >> 8) as f32/(1 << 24) as f32
+}
+
+/// Nested uniform (Owen) scrambling of the bits of x,
+/// using the hash of Laine & Karras on the reversed bits
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    let mut v = x.reverse_bits().wrapping_add(seed);
+    v ^= v.wrapping_mul(0x6c50b47c);
+    v ^= v.wrapping_mul(0xb82f1e52);
+    v ^= v.wrapping_mul(0xc7afe638);
+    v ^= v.wrapping_mul(0x8d22f6e6);
+    v.reverse_bits()
+}
+
+/// Owen-scrambled Sobol sequence
+#[derive(Clone)]
+pub struct Sobol {
+    index: u32,
+    directions: [[u32; 32]; MAX_DIM],
+    seeds: [u32; MAX_DIM],
+}
+
+impl Sobol {
+    pub fn new(rng: &mut SmallRng) -> Self {
+        let mut directions = [[0; 32]; MAX_DIM];
+        for (k, v) in directions[0].iter_mut().enumerate() {
+            *v = 1 << (31-k);
+        }
+        for (d, &(s, a, m)) in SOBOL_POLY.iter().enumerate() {
+            let v = &mut directions[d+1];
+            let s = s as usize;
+            for k in 0..s {
+                v[k] = m[k] << (31-k);
+            }
+            for k in s..32 {
+                v[k] = v[k-s] ^ (v[k-s] >> s);
+                for j in 1..s {
+                    v[k] ^= ((a >> (s-1-j)) & 1)*v[k-j];
+                }
+            }
+        }
+
+        Self {index: 0, directions, seeds: [(); MAX_DIM].map(|_| rng.gen())}
+    }
+
+    /// Unscrambled coordinate d of point i, as a 32 bit fraction
+    fn coordinate(&self, i: u32, d: usize) -> u32 {
+        (0..32)
+            .filter(|k| (i >> k) & 1 == 1)
+            .fold(0, |x, k| x ^ self.directions[d][k])
+    }
+}
+
+impl Candidates for Sobol {
+    fn point<const D: usize>(&mut self) -> [f32; D] {
+        assert!(D <= MAX_DIM, "err: dimension exceeds {}", MAX_DIM);
+        let i = self.index;
+        self.index = self.index.wrapping_add(1);
+        let mut d = 0;
+        [(); D].map(|_| {
+            d += 1;
+            to_unit(owen_scramble(self.coordinate(i, d-1), self.seeds[d-1]))
+        })
+    }
+}
+
+impl Split for Sobol {
+    fn split(&self, chunk: usize, len: usize) -> Self {
+        let mut gen = self.clone();
+        gen.skip(chunk*len);
+        gen
+    }
+
+    fn skip(&mut self, n: usize) {
+        self.index = self.index.wrapping_add(n as u32);
+    }
+}
+
+/// Halton sequence with random digit permutations per base, which keep
+/// zero fixed so that every point has finitely many nonzero digits
+#[derive(Clone)]
+pub struct Halton {
+    index: u32,
+    perms: Vec<Vec<u32>>,
+}
+
+impl Halton {
+    pub fn new(rng: &mut SmallRng) -> Self {
+        let perms = PRIMES.iter()
+            .map(|&b| {
+                let mut p = (1..b).collect::<Vec<_>>();
+                p.shuffle(rng);
+                p.insert(0, 0);
+                p
+            })
+            .collect();
+
+        Self {index: 1, perms}
+    }
+}
+
+impl Candidates for Halton {
+    fn point<const D: usize>(&mut self) -> [f32; D] {
+        assert!(D <= MAX_DIM, "err: dimension exceeds {}", MAX_DIM);
+        let i = self.index;
+        self.index = self.index.wrapping_add(1);
+        let mut d = 0;
+        [(); D].map(|_| {
+            let (b, perm) = (PRIMES[d], &self.perms[d]);
+            d += 1;
+            let (mut n, mut inv, mut x) = (i, 1.0/b as f64, 0.0);
+            while n > 0 {
+                x += perm[(n%b) as usize] as f64*inv;
+                n /= b;
+                inv /= b as f64;
+            }
+            (x as f32).min(1.0-f32::EPSILON)
+        })
+    }
+}
+
+impl Split for Halton {
+    fn split(&self, chunk: usize, len: usize) -> Self {
+        let mut gen = self.clone();
+        gen.skip(chunk*len);
+        gen
+    }
+
+    fn skip(&mut self, n: usize) {
+        self.index = self.index.wrapping_add(n as u32);
+    }
+}
+
+/// Additive recurrence of Roberts, xₙ = s + n·α mod 1 with αᵢ = g^-i for the
+/// generalized golden ratio g^(D+1) = g+1, scrambled by the random shift s
+#[derive(Clone)]
+pub struct Recurrence {
+    index: u32,
+    shift: [f64; MAX_DIM],
+}
+
+impl Recurrence {
+    pub fn new(rng: &mut SmallRng) -> Self {
+        Self {index: 0, shift: [(); MAX_DIM].map(|_| rng.gen())}
+    }
+}
+
+impl Candidates for Recurrence {
+    fn point<const D: usize>(&mut self) -> [f32; D] {
+        assert!(D <= MAX_DIM, "err: dimension exceeds {}", MAX_DIM);
+        let g = (0..16).fold(2.0f64, |g, _| (1.0+g).powf(1.0/(D as f64+1.0)));
+        let n = self.index as f64;
+        self.index = self.index.wrapping_add(1);
+        let mut alpha = 1.0;
+        let mut d = 0;
+        [(); D].map(|_| {
+            alpha /= g;
+            d += 1;
+            ((self.shift[d-1]+n*alpha).fract() as f32).min(1.0-f32::EPSILON)
+        })
+    }
+}
+
+impl Split for Recurrence {
+    fn split(&self, chunk: usize, len: usize) -> Self {
+        let mut gen = self.clone();
+        gen.skip(chunk*len);
+        gen
+    }
+
+    fn skip(&mut self, n: usize) {
+        self.index = self.index.wrapping_add(n as u32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// First points of the Joe & Kuo sequence in Gray code order, in sixteenths
+    const SOBOL_REFERENCE: [[u32; MAX_DIM]; 15] = [
+        [8, 8, 8, 8, 8, 8, 8, 8],
+        [12, 4, 4, 4, 12, 12, 4, 12],
+        [4, 12, 12, 12, 4, 4, 12, 4],
+        [6, 6, 10, 14, 6, 2, 6, 14],
+        [14, 14, 2, 6, 14, 10, 14, 6],
+        [10, 2, 14, 10, 10, 14, 2, 2],
+        [2, 10, 6, 2, 2, 6, 10, 10],
+        [3, 5, 15, 7, 9, 5, 7, 15],
+        [11, 13, 7, 15, 1, 13, 15, 7],
+        [15, 1, 11, 3, 5, 9, 3, 3],
+        [7, 9, 3, 11, 13, 1, 11, 11],
+        [5, 3, 5, 9, 15, 7, 1, 1],
+        [13, 11, 13, 1, 7, 15, 9, 9],
+        [9, 7, 1, 13, 3, 11, 5, 13],
+        [1, 15, 9, 5, 11, 3, 13, 5],
+    ];
+
+    /// Squared L2 star discrepancy, by the formula of Warnock
+    fn discrepancy<const D: usize>(points: &[[f32; D]]) -> f64 {
+        let n = points.len() as f64;
+        let single = points.iter()
+            .map(|p| p.iter().map(|&x| (1.0-(x as f64).powi(2))/2.0).product::<f64>())
+            .sum::<f64>();
+        let pairs = points.iter()
+            .flat_map(|p| points.iter().map(move |q| {
+                p.iter().zip(q).map(|(&x, &y)| 1.0-(x.max(y) as f64)).product::<f64>()
+            }))
+            .sum::<f64>();
+        3f64.powi(-(D as i32)) - 2.0*single/n + pairs/(n*n)
+    }
+
+    fn rng() -> SmallRng {
+        SmallRng::seed_from_u64(5)
+    }
+
+    #[test]
+    fn sobol_reference() {
+        let sobol = Sobol::new(&mut rng());
+        for (i, expected) in (1..16u32).zip(SOBOL_REFERENCE) {
+            for (d, &e) in expected.iter().enumerate() {
+                assert_eq!(sobol.coordinate(i ^ (i >> 1), d), e << 28, "point {} dim {}", i, d);
+            }
+        }
+    }
+
+    /// Scrambled points lie in [0, 1)^D and are more uniform than random ones,
+    /// whose expected squared discrepancy is (2^-D - 3^-D)/N
+    fn check_uniform<C: Candidates, const D: usize>(gen: &mut C, name: &str) {
+        const N: usize = 1024;
+        let points = (0..N).map(|_| gen.point::<D>()).collect::<Vec<_>>();
+        assert!(points.iter().flatten().all(|x| (0.0..1.0).contains(x)), "{}", name);
+        let random = (2f64.powi(-(D as i32))-3f64.powi(-(D as i32)))/N as f64;
+        let d = discrepancy(&points);
+        let bound = if D <= 3 { 0.15 } else { 0.6 };
+        assert!(d < bound*random, "{} D={}: {} vs random {}", name, D, d, random);
+    }
+
+    #[test]
+    fn uniform() {
+        fn check<const D: usize>() {
+            check_uniform::<_, D>(&mut Sobol::new(&mut rng()), "sobol");
+            check_uniform::<_, D>(&mut Halton::new(&mut rng()), "halton");
+            check_uniform::<_, D>(&mut Recurrence::new(&mut rng()), "recurrence");
+        }
+        check::<1>();
+        check::<2>();
+        check::<3>();
+        check::<5>();
+        check::<8>();
+    }
+
+    /// Split chunks and skips continue the sequence as if drawn one by one
+    fn check_split<C: Split + Clone>(gen: C, name: &str) {
+        let (chunks, len) = (4, 7);
+        let mut seq = gen.clone();
+        let sequential = (0..chunks*len+1).map(|_| seq.point::<3>()).collect::<Vec<_>>();
+        for c in 0..chunks {
+            let mut chunk = gen.split(c, len);
+            for i in 0..len {
+                assert_eq!(chunk.point::<3>(), sequential[c*len+i], "{} chunk {} point {}", name, c, i);
+            }
+        }
+        let mut skipped = gen.clone();
+        skipped.skip(chunks*len);
+        assert_eq!(skipped.point::<3>(), sequential[chunks*len], "{}", name);
+    }
+
+    #[test]
+    fn split() {
+        check_split(Sobol::new(&mut rng()), "sobol");
+        check_split(Halton::new(&mut rng()), "halton");
+        check_split(Recurrence::new(&mut rng()), "recurrence");
+    }
+}
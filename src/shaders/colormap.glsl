@@ -0,0 +1,15 @@
+#version 300 es
+precision mediump float;
+
+const float pi = 3.141592656;
+
+// Cyclic colormaps of the phase: 1 hue wheel, 2 red and blue by the sign
+// of the real part, through white at ±π/2
+vec3 colormap(in int map, in float phase) {
+    if (map == 1) {
+        float h = phase/(2.0*pi) + 0.5;
+        return clamp(abs(mod(6.0*h + vec3(0.0, 4.0, 2.0), 6.0) - 3.0) - 1.0, 0.0, 1.0);
+    }
+    float w = cos(phase);
+    return mix(vec3(1.0), w > 0.0 ? vec3(0.9, 0.2, 0.15) : vec3(0.15, 0.3, 0.9), abs(w));
+}
@@ -0,0 +1,31 @@
+#version 300 es
+precision highp float;
+
+#define RADIUS 4
+
+uniform sampler2D s_occlusion;
+uniform sampler2D s_gdata;
+// 0 to blur along x, 1 along y
+uniform int u_axis;
+// Falloff of the weights with the view-space depth difference
+uniform float u_sharpness;
+layout (location = 0) out float o_occlusion;
+
+void main() {
+    ivec2 xy = ivec2(gl_FragCoord.xy);
+    ivec2 size = textureSize(s_occlusion, 0);
+    ivec2 dir = u_axis == 0 ? ivec2(1, 0) : ivec2(0, 1);
+    float z = texelFetch(s_gdata, xy, 0).w;
+
+    // Gaussian weights, reduced across depth discontinuities
+    float sum = 0.0;
+    float weight = 0.0;
+    for (int i = -RADIUS; i <= RADIUS; i++) {
+        ivec2 p = clamp(xy + dir * i, ivec2(0), size - 1);
+        float s_z = texelFetch(s_gdata, p, 0).w;
+        float w = exp(-float(i * i) / 8.0 - abs(s_z - z) * u_sharpness);
+        sum += w * texelFetch(s_occlusion, p, 0).r;
+        weight += w;
+    }
+    o_occlusion = sum / weight;
+}
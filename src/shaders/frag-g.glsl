@@ -1,9 +1,10 @@
-#version 300 es
-precision mediump float;
-
+// Follows colormap.glsl
 uniform vec3 u_lightdir;
+// 0 for a solid colour, otherwise a colormap of the phase
+uniform int u_colormap;
 smooth in vec3 v_normal;
 smooth in vec4 v_pos;
+flat in float v_phase;
 layout (location = 0) out vec4 o_color;
 layout (location = 1) out vec4 o_gdata;
 
@@ -13,6 +14,9 @@ void main() {
     float diffuse = dot(normal, u_lightdir);
     float light = ambient + max(diffuse, 0.0);
     o_color = vec4(0.2, 1.0, 0.2, 1.0);
+    if (u_colormap > 0) {
+        o_color.rgb = colormap(u_colormap, v_phase);
+    }
     o_color.rgb *= light;
     o_gdata = vec4(v_normal, v_pos.z);
 }
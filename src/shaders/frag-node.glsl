@@ -0,0 +1,14 @@
+#version 300 es
+precision mediump float;
+
+uniform vec3 u_lightdir;
+uniform vec4 u_color;
+smooth in vec3 v_normal;
+layout (location = 0) out vec4 o_color;
+
+void main() {
+    // Surfaces are seen from both sides
+    vec3 normal = normalize(v_normal);
+    float light = 0.5 + abs(dot(normal, u_lightdir));
+    o_color = vec4(u_color.rgb*light, u_color.a);
+}
@@ -1,25 +1,62 @@
 #version 300 es
-precision mediump float;
+precision highp float;
+
+#define MAX_SAMPLES 64
 
-const float pi = 3.141592656;
-const float tg_h_fov = 0.411054915;
 uniform sampler2D s_gdata;
+uniform sampler2D s_noise;
+uniform mat4 u_proj;
 uniform float u_width;
 uniform float u_height;
+uniform float u_radius;
+uniform float u_intensity;
+uniform int u_samples;
+// Hemisphere around +z, denser towards the centre
+uniform vec4 u_kernel[MAX_SAMPLES];
 layout (location = 0) out float o_occlusion;
 
 void main() {
     vec2 xy = gl_FragCoord.xy;
     vec4 data = texelFetch(s_gdata, ivec2(xy), 0);
     float z = data.w;
-    vec3 norm = vec3(
-        data.x, data.y,
-        sqrt(1.0 - data.x * data.x - data.y * data.y)
-    );
+    // Background, w is cleared to 1 and geometry lies at negative z
+    if (z >= 0.0) {
+        o_occlusion = 0.0;
+        return;
+    }
+    // The G-buffer holds the full view-space normal, which may face
+    // along either sign of z under perspective
+    vec3 norm = normalize(data.xyz);
     vec3 view_ray = vec3(
-        (xy.x / u_width * 2.0 - 1.0) * tg_h_fov * u_width / u_height,
-        (xy.y / u_height * 2.0 - 1.0) * tg_h_fov, -1.0
+        (xy.x / u_width * 2.0 - 1.0) / u_proj[0][0],
+        (xy.y / u_height * 2.0 - 1.0) / u_proj[1][1], -1.0
     );
     vec3 pos = view_ray * -z;
-    o_occlusion = pos.x * 0.0;
+
+    // Kernel rotated about the normal by the tiled noise
+    vec3 rvec = vec3(texelFetch(s_noise, ivec2(xy) % textureSize(s_noise, 0), 0).xy, 0.0);
+    vec3 tangent = normalize(rvec - norm * dot(rvec, norm));
+    vec3 bitangent = cross(norm, tangent);
+    mat3 tbn = mat3(tangent, bitangent, norm);
+
+    float occlusion = 0.0;
+    for (int i = 0; i < MAX_SAMPLES; i++) {
+        if (i >= u_samples) {
+            break;
+        }
+        vec3 s = pos + tbn * u_kernel[i].xyz * u_radius;
+        vec4 clip = u_proj * vec4(s, 1.0);
+        vec2 uv = clip.xy / clip.w * 0.5 + 0.5;
+        if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+            continue;
+        }
+        float s_z = texture(s_gdata, uv).w;
+        if (s_z >= 0.0) {
+            continue;
+        }
+        // Occluders further than the radius from the point do not count
+        float range = smoothstep(0.0, 1.0, u_radius / abs(z - s_z));
+        occlusion += (s_z >= s.z + 0.025 ? 1.0 : 0.0) * range;
+    }
+    o_occlusion = u_intensity * occlusion / float(u_samples);
 }
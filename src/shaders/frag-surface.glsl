@@ -0,0 +1,24 @@
+// Follows colormap.glsl
+uniform vec3 u_lightdir;
+uniform vec4 u_color;
+// 0 for u_color, otherwise a colormap of the phase
+uniform int u_colormap;
+smooth in vec3 v_normal;
+smooth in vec4 v_pos;
+// Phase as a unit vector, which interpolates across ±π
+smooth in vec2 v_phase;
+layout (location = 0) out vec4 o_color;
+layout (location = 1) out vec4 o_gdata;
+
+void main() {
+    vec3 normal = normalize(v_normal);
+    float ambient = 0.5;
+    float diffuse = dot(normal, u_lightdir);
+    float light = ambient + max(diffuse, 0.0);
+    vec3 color = u_color.rgb;
+    if (u_colormap > 0) {
+        color = colormap(u_colormap, atan(v_phase.y, v_phase.x));
+    }
+    o_color = vec4(color*light, u_color.a);
+    o_gdata = vec4(v_normal, v_pos.z);
+}
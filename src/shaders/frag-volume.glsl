@@ -0,0 +1,70 @@
+#version 300 es
+precision highp float;
+precision highp sampler3D;
+
+const int max_steps = 512;
+const int n_transfer = 4;
+uniform sampler2D s_gdata;
+uniform sampler3D s_density;
+uniform mat4 u_inv_proj;
+uniform mat4 u_inv_view;
+uniform float u_scale;
+uniform float u_half;
+uniform int u_steps;
+uniform float u_emission;
+uniform float u_absorption;
+// Colour and opacity at densities evenly spaced over [0, 1]
+uniform vec4 u_transfer[n_transfer];
+layout (location = 0) out vec4 o_color;
+
+vec4 transfer(float density) {
+    float x = clamp(density, 0.0, 1.0) * float(n_transfer - 1);
+    int i = min(int(x), n_transfer - 2);
+    return mix(u_transfer[i], u_transfer[i + 1], x - float(i));
+}
+
+void main() {
+    vec2 xy = gl_FragCoord.xy;
+    vec4 data = texelFetch(s_gdata, ivec2(xy), 0);
+    vec2 ndc = xy / vec2(textureSize(s_gdata, 0)) * 2.0 - 1.0;
+    vec4 far = u_inv_proj * vec4(ndc, 1.0, 1.0);
+    vec3 view_dir = normalize(far.xyz / far.w);
+
+    // Ray in units of the half extent, from the camera through the pixel
+    float unit = u_scale * u_half;
+    vec3 origin = (u_inv_view * vec4(0.0, 0.0, 0.0, 1.0)).xyz / unit;
+    vec3 dir = mat3(u_inv_view) * view_dir;
+    vec3 t_a = (-1.0 - origin) / dir;
+    vec3 t_b = (1.0 - origin) / dir;
+    vec3 t_lo = min(t_a, t_b);
+    vec3 t_hi = max(t_a, t_b);
+    float t_near = max(max(max(t_lo.x, t_lo.y), t_lo.z), 0.0);
+    float t_far = min(min(t_hi.x, t_hi.y), t_hi.z);
+    // Geometry in the G-buffer ends the ray, w is 1 where there is none
+    if (data.w < 0.0) {
+        t_far = min(t_far, data.w / view_dir.z / unit);
+    }
+    if (t_far <= t_near) {
+        discard;
+    }
+
+    // Grid points at the box corners are texel centres
+    float n = float(textureSize(s_density, 0).x);
+    float dt = 2.0 * sqrt(3.0) / float(u_steps);
+    vec3 color = vec3(0.0);
+    float transmittance = 1.0;
+    for (int i = 0; i < max_steps; i++) {
+        float t = t_near + (float(i) + 0.5) * dt;
+        if (i >= u_steps || t > t_far || transmittance < 0.004) {
+            break;
+        }
+        vec3 pos = origin + t * dir;
+        vec3 uvw = ((pos * 0.5 + 0.5) * (n - 1.0) + 0.5) / n;
+        float density = texture(s_density, uvw).r;
+        vec4 tf = transfer(density);
+        float alpha = 1.0 - exp(-u_absorption * tf.a * dt);
+        color += transmittance * alpha * u_emission * tf.rgb;
+        transmittance *= 1.0 - alpha;
+    }
+    o_color = vec4(color, 1.0 - transmittance);
+}
@@ -4,19 +4,18 @@ precision highp float;
 uniform mat4 u_proj;
 uniform mat4 u_view;
 uniform float u_scale;
+// Position and arg ψ
 in vec4 i_pos;
 in vec4 a_pos;
 in vec3 a_normal;
 smooth out vec3 v_normal;
 smooth out vec4 v_pos;
+flat out float v_phase;
 
 void main() {
-    vec4 a_scaled = a_pos;
-    vec4 i_scaled = i_pos;
-    a_scaled.xyz *= 0.3;
-    i_scaled.xyz *= u_scale;
-    vec4 pos = a_scaled + i_scaled;
+    vec4 pos = vec4(a_pos.xyz*0.3 + i_pos.xyz*u_scale, 1.0);
     v_normal = mat3(u_view) * a_normal;
+    v_phase = i_pos.w;
     v_pos = u_view * pos;
     gl_Position = u_proj * v_pos;
 }
@@ -0,0 +1,15 @@
+#version 300 es
+precision highp float;
+
+uniform mat4 u_proj;
+uniform mat4 u_view;
+uniform float u_scale;
+in vec4 a_pos;
+in vec3 a_normal;
+smooth out vec3 v_normal;
+
+void main() {
+    vec4 pos = vec4(a_pos.xyz*u_scale, 1.0);
+    v_normal = mat3(u_view) * a_normal;
+    gl_Position = u_proj * u_view * pos;
+}
@@ -0,0 +1,20 @@
+#version 300 es
+precision highp float;
+
+uniform mat4 u_proj;
+uniform mat4 u_view;
+uniform float u_scale;
+in vec4 a_pos;
+in vec3 a_normal;
+in float a_phase;
+smooth out vec3 v_normal;
+smooth out vec4 v_pos;
+smooth out vec2 v_phase;
+
+void main() {
+    vec4 pos = vec4(a_pos.xyz*u_scale, 1.0);
+    v_normal = mat3(u_view) * a_normal;
+    v_pos = u_view * pos;
+    v_phase = vec2(cos(a_phase), sin(a_phase));
+    gl_Position = u_proj * v_pos;
+}
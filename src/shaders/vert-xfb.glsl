@@ -1,10 +1,22 @@
 #version 300 es
 precision highp float;
 
-const float hbar_me = 1.15767636e-4;
+#define MAX_TERMS 4
+#define MAX_STEPS 19
+
 uniform float u_dt;
-in vec3 i_pos;
-flat out vec3 v_pos;
+uniform float u_time;
+uniform float u_inv_mass;
+uniform int u_n_terms;
+// Per term (c, N, l-|m|, |m|) and (Re c_k, Im c_k, E_k, mode)
+uniform vec4 u_term_a[MAX_TERMS];
+uniform vec4 u_term_b[MAX_TERMS];
+// Recurrence steps (a, b, c, 0) of the Laguerre and Legendre polynomials
+uniform vec4 u_lag[MAX_TERMS*MAX_STEPS];
+uniform vec4 u_leg[MAX_TERMS*MAX_STEPS];
+// Position and arg ψ
+in vec4 i_pos;
+flat out vec4 v_pos;
 
 vec2 conj(in vec2 z) {
     return vec2(z.x, -z.y);
@@ -14,7 +26,135 @@ float conj_mul(in vec2 z) {
     return dot(z, z);
 }
 
+vec2 cmul(in vec2 a, in vec2 b) {
+    return vec2(a.x*b.x - a.y*b.y, a.x*b.y + a.y*b.x);
+}
+
+// p_(j+1) = (a + b·x)·p_j - c·p_(j-1) from p_0 = 1, and its derivative
+vec2 recurrence_lag(in int t, in float x) {
+    float p = 1.0;
+    float p_1 = 0.0;
+    float d = 0.0;
+    float d_1 = 0.0;
+    for (int i = 0; i < MAX_STEPS; i++) {
+        vec4 s = u_lag[t*MAX_STEPS + i];
+        float q = (s.x + s.y*x)*p - s.z*p_1;
+        float e = s.y*p + (s.x + s.y*x)*d - s.z*d_1;
+        p_1 = p;
+        p = q;
+        d_1 = d;
+        d = e;
+    }
+    return vec2(p, d);
+}
+
+vec2 recurrence_leg(in int t, in float x) {
+    float p = 1.0;
+    float p_1 = 0.0;
+    float d = 0.0;
+    float d_1 = 0.0;
+    for (int i = 0; i < MAX_STEPS; i++) {
+        vec4 s = u_leg[t*MAX_STEPS + i];
+        float q = (s.x + s.y*x)*p - s.z*p_1;
+        float e = s.y*p + (s.x + s.y*x)*d - s.z*d_1;
+        p_1 = p;
+        p = q;
+        d_1 = d;
+        d = e;
+    }
+    return vec2(p, d);
+}
+
+// ψ = N·L(ρ)·e^(-ρ/2)·ρ^(l-|m|)·P(z/r)·(c·w)^|m| with ρ = c·r, and its gradient
+void psi_term(
+    in int t, in vec3 p,
+    out vec2 psi, out vec2 d_x, out vec2 d_y, out vec2 d_z
+) {
+    vec4 a = u_term_a[t];
+    float mode = u_term_b[t].w;
+    float c = a.x;
+    float lm = a.z;
+    float r = length(p);
+    vec3 dr = p/r;
+    float rho = c*r;
+    vec2 lag = recurrence_lag(t, rho);
+    float e = exp(-0.5*rho);
+    float rl = lm > 0.0 ? pow(rho, lm) : 1.0;
+    float f = lag.x*e*rl;
+    float df = c*e*(rl*(lag.y - 0.5*lag.x) + (lm > 0.0 ? lm*pow(rho, lm-1.0)*lag.x : 0.0));
+    float ct = p.z/r;
+    vec3 dct = (vec3(0.0, 0.0, 1.0) - dr*ct)/r;
+    vec2 leg = recurrence_leg(t, ct);
+
+    float s = mode == 1.0 ? -1.0 : 1.0;
+    vec2 w = c*vec2(p.x, s*p.y);
+    vec2 h = vec2(1.0, 0.0);
+    vec2 dh = vec2(0.0, 0.0);
+    for (int i = 0; i < int(a.w); i++) {
+        dh = cmul(dh, w) + h;
+        h = cmul(h, w);
+    }
+    vec2 dhx = c*dh;
+    vec2 dhy = cmul(c*dh, vec2(0.0, s));
+    if (mode == 2.0) {
+        h = vec2(h.x, 0.0);
+        dhx = vec2(dhx.x, 0.0);
+        dhy = vec2(dhy.x, 0.0);
+    } else if (mode == 3.0) {
+        h = vec2(h.y, 0.0);
+        dhx = vec2(dhx.y, 0.0);
+        dhy = vec2(dhy.y, 0.0);
+    }
+
+    float fg = a.y*f*leg.x;
+    vec3 d_fg = (dr*(df*leg.x) + dct*(f*leg.y))*a.y;
+
+    psi = h*fg;
+    d_x = h*d_fg.x + dhx*fg;
+    d_y = h*d_fg.y + dhy*fg;
+    d_z = h*d_fg.z;
+}
+
+// Guiding equation v = (ħ/μ) Im(∇ψ/ψ), and ψ
+vec3 velocity(in vec3 p, in float time, out vec2 psi) {
+    psi = vec2(0.0);
+    vec2 g_x = vec2(0.0);
+    vec2 g_y = vec2(0.0);
+    vec2 g_z = vec2(0.0);
+    for (int t = 0; t < u_n_terms; t++) {
+        vec4 b = u_term_b[t];
+        vec2 phase = cmul(b.xy, vec2(cos(-b.z*time), sin(-b.z*time)));
+        vec2 v, d_x, d_y, d_z;
+        psi_term(t, p, v, d_x, d_y, d_z);
+        psi += cmul(v, phase);
+        g_x += cmul(d_x, phase);
+        g_y += cmul(d_y, phase);
+        g_z += cmul(d_z, phase);
+    }
+    float n = conj_mul(psi);
+    if (n < 1e-30) {
+        return vec3(0.0);
+    }
+    vec2 c = conj(psi);
+    return vec3(cmul(g_x, c).y, cmul(g_y, c).y, cmul(g_z, c).y)/n*u_inv_mass;
+}
+
 void main() {
-    float v = 0.0;
-    v_pos = i_pos+v*u_dt;
+    float h = u_dt;
+    vec3 p = i_pos.xyz;
+    vec2 psi;
+    vec3 k1 = velocity(p, u_time, psi);
+    vec3 k2 = velocity(p + 0.5*h*k1, u_time + 0.5*h, psi);
+    vec3 k3 = velocity(p + 0.5*h*k2, u_time + 0.5*h, psi);
+    vec3 k4 = velocity(p + h*k3, u_time + h, psi);
+    vec3 q = p + h/6.0*(k1 + 2.0*k2 + 2.0*k3 + k4);
+    // Phase at the end of the step, real states keep their sign
+    // without the time evolution and the phase of their amplitude
+    velocity(q, u_time + h, psi);
+    vec4 b = u_term_b[0];
+    if (u_n_terms == 1 && (b.w >= 2.0 || u_term_a[0].w == 0.0)) {
+        float e = b.z*(u_time + h);
+        psi = cmul(psi, cmul(conj(b.xy), vec2(cos(e), sin(e))));
+    }
+    v_pos = vec4(q, atan(psi.y, psi.x));
 }
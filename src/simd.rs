@@ -0,0 +1,156 @@
+// Elementwise kernels over f32 slices. With the simd128 target feature they
+// run on v128 lanes, otherwise the scalar fallback performs the same sequence
+// of IEEE operations, so both paths give bit-identical results.
+
+#[cfg(all(feature = "simd128", target_arch = "wasm32", not(target_feature = "simd128")))]
+compile_error!("err: the simd128 feature needs -Ctarget-feature=+simd128, see build.sh");
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+use core::arch::wasm32::*;
+use std::f32::consts::LOG2_E;
+
+// Range of exp without overflow to infinity or into subnormals
+const EXP_HI: f32 = 88.0;
+const EXP_LO: f32 = -87.33655;
+// ln 2 split into an exact high part and a correction (Cephes expf)
+const LN2_HI: f32 = 0.693_359_4;
+const LN2_LO: f32 = -2.121_944_4e-4;
+// Minimax polynomial for (e^r - 1 - r)/r² on [-ln2/2, ln2/2], highest order first
+const EXP_POLY: [f32; 6] = [
+    1.987_569_1e-4, 1.398_199_9e-3, 8.333_452e-3,
+    4.166_579_6e-2, 1.666_666_5e-1, 5.0e-1,
+];
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn map_lanes(
+    v: &mut [f32],
+    lanes: impl Fn(v128) -> v128,
+    scalar: impl Fn(f32) -> f32,
+) {
+    let mut chunks = v.chunks_exact_mut(4);
+    for c in &mut chunks {
+        let p = c.as_mut_ptr() as *mut v128;
+        unsafe { v128_store(p, lanes(v128_load(p))) };
+    }
+    for x in chunks.into_remainder() {
+        *x = scalar(*x);
+    }
+}
+
+fn exp_scalar(x: f32) -> f32 {
+    let x = x.clamp(EXP_LO, EXP_HI);
+    let n = (x*LOG2_E+0.5).floor();
+    let r = x-n*LN2_HI-n*LN2_LO;
+    let p = EXP_POLY.iter().fold(0.0, |p, c| p*r+c);
+    let y = p*r*r+r+1.0;
+    y*f32::from_bits(((n as i32+127) << 23) as u32)
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+fn exp_lanes(x: v128) -> v128 {
+    let x = f32x4_min(f32x4_max(x, f32x4_splat(EXP_LO)), f32x4_splat(EXP_HI));
+    let n = f32x4_floor(f32x4_add(f32x4_mul(x, f32x4_splat(LOG2_E)), f32x4_splat(0.5)));
+    let r = f32x4_sub(
+        f32x4_sub(x, f32x4_mul(n, f32x4_splat(LN2_HI))),
+        f32x4_mul(n, f32x4_splat(LN2_LO)),
+    );
+    let p = EXP_POLY.iter().fold(f32x4_splat(0.0), |p, c| {
+        f32x4_add(f32x4_mul(p, r), f32x4_splat(*c))
+    });
+    let y = f32x4_add(f32x4_add(f32x4_mul(f32x4_mul(p, r), r), r), f32x4_splat(1.0));
+    let e = i32x4_shl(i32x4_add(i32x4_trunc_sat_f32x4(n), i32x4_splat(127)), 23);
+    f32x4_mul(y, e)
+}
+
+/// e^x in place, within 2 ulp of the correctly rounded result
+pub fn exp(v: &mut [f32]) {
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    map_lanes(v, exp_lanes, exp_scalar);
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+    v.iter_mut().for_each(|x| *x = exp_scalar(*x));
+}
+
+/// Square root in place
+pub fn sqrt(v: &mut [f32]) {
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    map_lanes(v, |x| f32x4_sqrt(x), f32::sqrt);
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+    v.iter_mut().for_each(|x| *x = x.sqrt());
+}
+
+fn recurrence_scalar(steps: &[[f32; 3]], x: f32) -> f32 {
+    steps.iter().fold((1.0, 0.0), |(p, p_1), [a, b, c]| {
+        ((a+b*x)*p-c*p_1, p)
+    }).0
+}
+
+/// Three-term recurrence pⱼ₊₁ = (a + b·x)·pⱼ - c·pⱼ₋₁ from p₀ = 1 in place,
+/// see special::Recurrence
+pub fn recurrence(steps: &[[f32; 3]], v: &mut [f32]) {
+    let scalar = |x: f32| recurrence_scalar(steps, x);
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    map_lanes(v, |x| {
+        let init = (f32x4_splat(1.0), f32x4_splat(0.0));
+        steps.iter().fold(init, |(p, p_1), [a, b, c]| {
+            let q = f32x4_mul(f32x4_add(f32x4_splat(*a), f32x4_mul(f32x4_splat(*b), x)), p);
+            (f32x4_sub(q, f32x4_mul(f32x4_splat(*c), p_1)), p)
+        }).0
+    }, scalar);
+    #[cfg(not(all(target_arch = "wasm32", target_feature = "simd128")))]
+    v.iter_mut().for_each(|x| *x = scalar(*x));
+}
+
+// With the simd128 target feature these compare both paths, run with
+// wasm-bindgen-test-runner as in build.sh
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::special::Recurrence;
+    #[cfg(target_arch = "wasm32")]
+    use wasm_bindgen_test::wasm_bindgen_test as test;
+
+    /// Inputs across the range of each kernel, with lengths that leave remainders
+    fn inputs(lo: f32, hi: f32) -> Vec<f32> {
+        let n = 4099;
+        let mut v: Vec<f32> = (0..n).map(|i| lo+(hi-lo)*i as f32/(n-1) as f32).collect();
+        v.extend([0.0, -0.0, 1.0, f32::MIN_POSITIVE, f32::EPSILON]);
+        v
+    }
+
+    fn assert_bits(out: &[f32], expected: impl Fn(usize) -> f32) {
+        for (i, o) in out.iter().enumerate() {
+            assert_eq!(o.to_bits(), expected(i).to_bits(), "at {}: {} vs {}", i, o, expected(i));
+        }
+    }
+
+    #[test]
+    fn exp_matches_scalar() {
+        let x = inputs(-100.0, 100.0);
+        let mut v = x.clone();
+        exp(&mut v);
+        assert_bits(&v, |i| exp_scalar(x[i]));
+        for (x, y) in x.iter().zip(&v).filter(|(x, _)| (EXP_LO..=EXP_HI).contains(*x)) {
+            let exact = (*x as f64).exp();
+            assert!(((*y as f64-exact)/exact).abs() <= 2.0*f32::EPSILON as f64, "e^{} = {}", x, y);
+        }
+    }
+
+    #[test]
+    fn sqrt_matches_scalar() {
+        let x = inputs(0.0, 1e4);
+        let mut v = x.clone();
+        sqrt(&mut v);
+        assert_bits(&v, |i| x[i].sqrt());
+    }
+
+    #[test]
+    fn recurrence_matches_scalar() {
+        for poly in [Recurrence::laguerre(9, 11.0), Recurrence::legendre(12, 3)] {
+            let steps = poly.to_f32().steps().to_vec();
+            let x = inputs(-1.0, 40.0);
+            let mut v = x.clone();
+            recurrence(&steps, &mut v);
+            assert_bits(&v, |i| recurrence_scalar(&steps, x[i]));
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use nalgebra::{DMatrix, RealField};
+
+/// Polynomial given by the three-term recurrence p₋₁ = 0, p₀ = 1,
+/// pⱼ₊₁ = (aⱼ + bⱼ·x)·pⱼ - cⱼ·pⱼ₋₁, with one step (aⱼ, bⱼ, cⱼ) per degree.
+/// Unlike monomial coefficients this stays accurate at high degree.
+#[derive(Clone, Debug)]
+pub struct Recurrence<T> {
+    steps: Vec<[T; 3]>,
+}
+
+impl<T: RealField + Copy> Recurrence<T> {
+    pub fn steps(&self) -> &[[T; 3]] {
+        &self.steps
+    }
+
+    pub fn degree(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Value and derivative at x
+    pub fn eval(&self, x: T) -> (T, T) {
+        let (mut p, mut p_1) = (T::one(), T::zero());
+        let (mut d, mut d_1) = (T::zero(), T::zero());
+        for &[a, b, c] in &self.steps {
+            let q = (a+b*x)*p-c*p_1;
+            let e = b*p+(a+b*x)*d-c*d_1;
+            (p_1, p, d_1, d) = (p, q, d, e);
+        }
+        (p, d)
+    }
+}
+
+impl Recurrence<f64> {
+    /// Generalized Laguerre polynomial L_k^α(x)
+    pub fn laguerre(k: u32, alpha: f64) -> Self {
+        let steps = (0..k)
+            .map(|j| {
+                let j = j as f64;
+                [(2.0*j+1.0+alpha)/(j+1.0), -1.0/(j+1.0), (j+alpha)/(j+1.0)]
+            })
+            .collect();
+
+        Self {steps}
+    }
+
+    /// Polynomial part √((2l+1)/4π·(l-m)!/(l+m)!)·dᵐ/dxᵐ P_l(x) of the
+    /// normalized associated Legendre function, relative to its value at l = m.
+    /// Normalized steps keep the values moderate for any l and m.
+    pub fn legendre(l: u32, m: u32) -> Self {
+        let m = m as f64;
+        let steps = (m as u32..l)
+            .map(|j| {
+                let j = j as f64;
+                let a = ((2.0*j+1.0)*(2.0*j+3.0)/((j+1.0-m)*(j+1.0+m))).sqrt();
+                let b = if j > m {
+                    ((2.0*j+3.0)*(j-m)*(j+m)/((2.0*j-1.0)*(j+1.0-m)*(j+1.0+m))).sqrt()
+                } else {0.0};
+                [0.0, a, b]
+            })
+            .collect();
+
+        Self {steps}
+    }
+
+    /// Roots in increasing order, as eigenvalues of the symmetrized Jacobi
+    /// matrix (Golub-Welsch) polished by Newton steps. All roots are real and
+    /// simple for orthogonal polynomials.
+    pub fn roots(&self) -> Vec<f64> {
+        let k = self.steps.len();
+        if k == 0 {
+            return Vec::new();
+        }
+        let jacobi = DMatrix::from_fn(k, k, |i, j| {
+            if i == j {
+                -self.steps[i][0]/self.steps[i][1]
+            } else if i.abs_diff(j) == 1 {
+                let i = i.max(j);
+                (self.steps[i][2]/(self.steps[i-1][1]*self.steps[i][1])).sqrt()
+            } else {0.0}
+        });
+        let mut roots = jacobi.symmetric_eigenvalues()
+            .iter()
+            .map(|&x| (0..2).fold(x, |x, _| {
+                let (p, d) = self.eval(x);
+                if d != 0.0 {x-p/d} else {x}
+            }))
+            .collect::<Vec<_>>();
+        roots.sort_by(f64::total_cmp);
+        roots
+    }
+
+    pub fn to_f32(&self) -> Recurrence<f32> {
+        Recurrence {steps: self.steps.iter().map(|s| s.map(|v| v as f32)).collect()}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    const N_MAX: u32 = 20;
+
+    fn ln_fact(n: u32) -> f64 {
+        (2..=n).map(|i| (i as f64).ln()).sum()
+    }
+
+    fn binomial(n: u32, k: u32) -> f64 {
+        (ln_fact(n)-ln_fact(k)-ln_fact(n-k)).exp()
+    }
+
+    /// Value of a polynomial from its (coefficient, power) terms, and the sum
+    /// of their magnitudes which bounds the rounding error
+    fn sum(terms: impl Iterator<Item = (f64, i32)>, x: f64) -> (f64, f64) {
+        terms.fold((0.0, 0.0), |(v, s), (c, k)| (v+c*x.powi(k), s+(c*x.powi(k)).abs()))
+    }
+
+    /// L_k^α(x) = Σᵢ (-1)ⁱ C(k+α, k-i) xⁱ/i!
+    fn laguerre(k: u32, alpha: u32, x: f64) -> (f64, f64) {
+        let terms = (0..=k).map(|i| {
+            let sign = if i%2 == 0 {1.0} else {-1.0};
+            (sign*binomial(k+alpha, k-i)/ln_fact(i).exp(), i as i32)
+        });
+        sum(terms, x)
+    }
+
+    /// √((2l+1)/4π·(l-m)!/(l+m)!)·dᵐ/dxᵐ P_l(x), with Rodrigues' expansion
+    /// P_l(x) = 2⁻ˡ Σⱼ (-1)ʲ C(l, j) C(2l-2j, l) x^(l-2j)
+    fn legendre(l: u32, m: u32, x: f64) -> (f64, f64) {
+        let norm = (((2*l+1) as f64/(4.0*PI)).ln()+ln_fact(l-m)-ln_fact(l+m)).exp().sqrt();
+        let terms = (0..=(l-m)/2).map(|j| {
+            let sign = if j%2 == 0 {1.0} else {-1.0};
+            let k = l-2*j;
+            // m-th derivative of x^k
+            let d = (ln_fact(k)-ln_fact(k-m)).exp();
+            (norm*sign*binomial(l, j)*binomial(2*l-2*j, l)*d/2f64.powi(l as i32), (k-m) as i32)
+        });
+        sum(terms, x)
+    }
+
+    #[test]
+    fn laguerre_closed_form() {
+        for k in 0..N_MAX {
+            for alpha in (1..2*N_MAX).step_by(2) {
+                let poly = Recurrence::laguerre(k, alpha as f64);
+                for i in 0..=40 {
+                    let x = 0.1*(i*i) as f64;
+                    let (expected, scale) = laguerre(k, alpha, x);
+                    let value = poly.eval(x).0;
+                    assert!((value-expected).abs() <= 1e-12*scale.max(1.0), "L_{}^{}({}) = {} vs {}", k, alpha, x, value, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn legendre_closed_form() {
+        for l in 0..N_MAX {
+            for m in 0..=l {
+                let poly = Recurrence::legendre(l, m);
+                let (p_mm, _) = legendre(m, m, 0.0);
+                for i in 0..=40 {
+                    let x = i as f64/20.0-1.0;
+                    let (expected, scale) = legendre(l, m, x);
+                    let value = poly.eval(x).0*p_mm;
+                    assert!((value-expected).abs() <= 1e-12*scale.max(1.0), "P_{}^{}({}) = {} vs {}", l, m, x, value, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn roots() {
+        // Roots of P_2, P_3 and P_4
+        let known = [
+            vec![-(1.0f64/3.0).sqrt(), (1.0f64/3.0).sqrt()],
+            vec![-0.6f64.sqrt(), 0.0, 0.6f64.sqrt()],
+            vec![-0.861_136_311_594_052_6, -0.339_981_043_584_856_3, 0.339_981_043_584_856_3, 0.861_136_311_594_052_6],
+        ];
+        for (l, expected) in (2..).zip(known) {
+            let roots = Recurrence::legendre(l, 0).roots();
+            for (r, e) in roots.iter().zip(&expected) {
+                assert!((r-e).abs() < 1e-14, "P_{}: {:?}", l, roots);
+            }
+        }
+
+        for k in 1..N_MAX {
+            for alpha in (1..2*N_MAX).step_by(2) {
+                let roots = Recurrence::laguerre(k, alpha as f64).roots();
+                assert_eq!(roots.len(), k as usize);
+                assert!(roots.windows(2).all(|r| r[0] < r[1]) && roots[0] > 0.0);
+                // Vieta: the roots sum to k(k+α) and multiply to (k+α)!/α!
+                let sum = roots.iter().sum::<f64>();
+                let ln_prod = roots.iter().map(|r| r.ln()).sum::<f64>();
+                assert!((sum/(k*(k+alpha)) as f64-1.0).abs() < 1e-12, "L_{}^{}: {:?}", k, alpha, roots);
+                assert!((ln_prod-ln_fact(k+alpha)+ln_fact(alpha)).abs() < 1e-10, "L_{}^{}: {:?}", k, alpha, roots);
+                for r in roots {
+                    let (v, scale) = laguerre(k, alpha, r);
+                    assert!(v.abs() <= 1e-12*scale, "L_{}^{}({}) = {}", k, alpha, r, v);
+                }
+            }
+        }
+
+        for l in 1..N_MAX {
+            for m in 0..l {
+                let roots = Recurrence::legendre(l, m).roots();
+                assert_eq!(roots.len(), (l-m) as usize);
+                assert!(roots.windows(2).all(|r| r[0] < r[1]));
+                assert!(roots.iter().all(|r| r.abs() < 1.0));
+                // Roots are symmetric about 0
+                for (a, b) in roots.iter().zip(roots.iter().rev()) {
+                    assert!((a+b).abs() < 1e-13, "P_{}^{}: {:?}", l, m, roots);
+                }
+                for r in roots {
+                    let (v, scale) = legendre(l, m, r);
+                    assert!(v.abs() <= 1e-12*scale, "P_{}^{}({}) = {}", l, m, r, v);
+                }
+            }
+        }
+    }
+}
@@ -1,55 +1,608 @@
-use std::f32::consts::{E, PI};
-use bacon_sci::polynomial; // Macro
-use bacon_sci::polynomial::Polynomial;
-use bacon_sci::special::{laguerre, legendre};
-use nalgebra::{SVector, Complex, ComplexField};
+use std::f64::consts::PI;
+use std::cell::{Cell, RefCell};
+use nalgebra::{SVector, Complex};
+use crate::dual::Dual;
+use crate::simd;
+use crate::special::Recurrence;
 
 type Cf32 = Complex<f32>;
 
-fn fact_stirling(n: f32) -> f32 {
-    if n <= 1.0 { 1.0 } else {
-        (2.0*PI*n).sqrt()*(n/E).powf(n)
+fn ln_fact(n: u32) -> f64 {
+    (2..=n).map(|i| (i as f64).ln()).sum()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuantumNumbers {
+    n: u32,
+    l: u32,
+    m: i32,
+}
+
+impl QuantumNumbers {
+    pub fn new(n: u32, l: u32, m: i32) -> Result<Self, String> {
+        if n == 0 {
+            Err("err: n must be at least 1".into())
+        } else if l >= n {
+            Err(format!("err: l must be less than n (n={}, l={})", n, l))
+        } else if m.unsigned_abs() > l {
+            Err(format!("err: |m| must not exceed l (l={}, m={})", l, m))
+        } else {
+            Ok(Self {n, l, m})
+        }
     }
+
+    pub fn n(&self) -> u32 { self.n }
+    pub fn l(&self) -> u32 { self.l }
+    pub fn m(&self) -> i32 { self.m }
+}
+
+const SUBSHELLS: &[u8] = b"spdfghiklmnoqrtuvwxyz";
+// Even number of intervals for radial expectation values
+const MOMENT_STEPS: usize = 4096;
+
+/// Proton mass in units of the electron mass
+pub const M_PROTON: f32 = 1836.1527;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Basis {
+    /// Eigenfunctions of L_z with phase e^{imφ}
+    #[default]
+    Complex,
+    /// Tesseral harmonics, cos(mφ) for m > 0 and sin(|m|φ) for m < 0
+    Real,
 }
 
-fn derive(n: u32, poly: Polynomial<f32>) -> Polynomial<f32> {
-    let mut poly_d = poly.clone();
-    for _ in 1..=n {
-        poly_d = poly_d.derivative();
+pub struct PsiBuilder {
+    qn: QuantumNumbers,
+    basis: Basis,
+    charge: u32,
+    mu: f32,
+}
+
+impl PsiBuilder {
+    pub fn basis(mut self, basis: Basis) -> Self {
+        self.basis = basis;
+        self
+    }
+
+    /// Nuclear charge Z, e.g. 2 for He⁺ or 3 for Li²⁺
+    pub fn charge(mut self, charge: u32) -> Self {
+        self.charge = charge.max(1);
+        self
+    }
+
+    /// Reduced mass correction for a particle orbiting a nucleus of finite mass,
+    /// both in units of the electron mass, e.g. (1.0, 1.0) for positronium
+    pub fn reduced_mass(mut self, particle: f32, nucleus: f32) -> Self {
+        self.mu = particle*nucleus/(particle+nucleus);
+        self
+    }
+
+    pub fn build(self) -> Psi {
+        let QuantumNumbers {n, l, m} = self.qn;
+        let m_abs = m.unsigned_abs();
+        let p = 2*l+1;
+        let q = n-l-1;
+        let a = 1.0/(self.charge as f64*self.mu as f64);
+        let energy = -0.5*(self.charge*self.charge) as f32*self.mu/(n*n) as f32;
+        let r = 2.0/(n as f64*a);
+        let s = ln_fact(n+l);
+        let t = ln_fact(q);
+        let u = (r.powi(3)/(2.0*n as f64)*(t-s).exp()).sqrt();
+        // Normalized polar part at l = |m|, √((2m+1)/4π·(2m)!)/(2^m·m!)
+        let v = ln_fact(2*m_abs);
+        let w = ln_fact(m_abs);
+        let real = self.basis == Basis::Real && m != 0;
+        let o = (0.5*(((2*m_abs+1) as f64/(4.0*PI)).ln()+v)-m_abs as f64*2f64.ln()-w).exp()
+            *if real {2f64.sqrt()} else {1.0};
+        // Condon-Shortley phase, cancels for m < 0 (Y_l^-m = (-1)^m conj(Y_l^m))
+        // and is omitted for real orbitals
+        let cs = m > 0 && self.basis == Basis::Complex;
+        let o = if cs && m%2 == 1 {-o} else {o};
+        let k = 0.5*m_abs as f32;
+
+        let e = if self.basis == Basis::Real {m_abs as f32} else {m as f32};
+
+        let coeffs = [r as f32,u as f32,k,o as f32,l as f32,e].map(Complex::from);
+
+        let leg = Recurrence::legendre(l, m_abs);
+        let lag = Recurrence::laguerre(q, p as f64);
+
+        Psi {
+            qn: self.qn,
+            basis: self.basis,
+            scale: a as f32,
+            mu: self.mu,
+            energy,
+            coeffs,
+            coeffs_f64: [r, u, o],
+            leg_f32: leg.to_f32(),
+            lag_f32: lag.to_f32(),
+            leg,
+            lag,
+        }
     }
-    poly_d
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 { a } else { gcd(b, a%b) }
+}
+
+/// Point group of |ψ|² with respect to the z axis
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symmetry {
+    /// Order of the rotation axis along z, 0 for full axial symmetry
+    pub rotation: u32,
+    /// Mirror planes containing the z axis, one of them being y = 0
+    pub vertical: bool,
+    /// Mirror plane z = 0
+    pub horizontal: bool,
+}
+
+impl Symmetry {
+    pub const TRIVIAL: Self = Self {rotation: 1, vertical: false, horizontal: false};
+
+    fn of<'a>(states: impl Iterator<Item = &'a Psi> + Clone) -> Self {
+        let Some(first) = states.clone().next() else { return Self::TRIVIAL };
+        let m_0 = first.qn.m;
+        // Rotating by 2π/k leaves |ψ|² invariant if all e^{imφ} components pick up
+        // the same phase, i.e. k divides the differences between their m
+        let rotation = states.clone()
+            .flat_map(|psi| match psi.basis {
+                Basis::Complex => [psi.qn.m, psi.qn.m],
+                Basis::Real => [psi.qn.m.abs(), -psi.qn.m.abs()],
+            })
+            .fold(0, |k, m| gcd(k, m.abs_diff(m_0)));
+        // Real orbitals are even (m ≥ 0) or odd (m < 0) in y
+        let vertical = rotation != 0 && states.clone()
+            .all(|psi| psi.basis == Basis::Real && (psi.qn.m >= 0) == (m_0 >= 0));
+        // Parity in z is (-1)^(l-|m|)
+        let parity = |psi: &Psi| (psi.qn.l-psi.qn.m.unsigned_abs())%2;
+        let horizontal = states.clone().all(|psi| parity(psi) == parity(first));
+
+        Self {rotation, vertical, horizontal}
+    }
+}
+
+/// Intermediate values of Psi::eval_into, kept between calls
+#[derive(Default)]
+struct Scratch {
+    rho: Vec<f32>,
+    lag: Vec<f32>,
+    exp: Vec<f32>,
+    leg: Vec<f32>,
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Scratch> = RefCell::default();
+    static TERM: Cell<Vec<Cf32>> = Cell::default();
+}
+
+/// Factorized form ψ = N·L(ρ)·e^{-ρ/2}·ρ^(l-|m|)·P(z/r)·(c·w)^|m| with ρ = c·r
+/// and w = x+iy (or x-iy for m < 0), which is regular on the z axis.
+/// Real orbitals take the real (mode 2) or imaginary (mode 3) part of w^|m|.
+/// The polynomials are given by their recurrence steps, see special::Recurrence.
+pub struct Factors {
+    pub c: f32,
+    pub norm: f32,
+    pub l_m: u32,
+    pub m_abs: u32,
+    pub mode: u32,
+    pub lag: Vec<[f32; 3]>,
+    pub leg: Vec<[f32; 3]>,
+}
+
+/// Nodal surfaces of a single state
+#[derive(Default)]
+pub struct Nodes {
+    /// Radii of the spherical radial nodes
+    pub radial: Vec<f32>,
+    /// Polar angles θ of the nodal cones
+    pub cones: Vec<f32>,
+    /// Azimuthal angles φ of the nodal planes containing the z axis
+    pub planes: Vec<f32>,
 }
 
 pub struct Psi {
+    qn: QuantumNumbers,
+    basis: Basis,
+    scale: f32,
+    mu: f32,
+    energy: f32,
     coeffs: [Cf32; 6],
-    leg_poly: Polynomial<f32>,
-    lag_poly: Polynomial<f32>,
+    /// c, radial and polar normalization in double precision
+    coeffs_f64: [f64; 3],
+    leg: Recurrence<f64>,
+    lag: Recurrence<f64>,
+    leg_f32: Recurrence<f32>,
+    lag_f32: Recurrence<f32>,
 }
 
 impl Psi {
-    pub fn new(n: u32, l: u32, m: u32) -> Self {
-        let p = 2*l+1;
-        let q = n-l-1;
-        let r = 2.0/n as f32;
-        let s = fact_stirling((n+l) as f32);
-        let t = fact_stirling(q as f32);
-        let u = (r.powf(3.0)/(2.0*(s/t)*n as f32)).sqrt();
-        let v = fact_stirling((l-m) as f32);
-        let w = fact_stirling((l+m) as f32);
-        let o = (v/(w*4.0*PI)*p as f32).sqrt();
-        let k = 0.5*m as f32;
-        let tol = 1e-8;
-
-        let coeffs = [r,u,k,o,l as f32,m as f32].map(Complex::from);
-
-        let scalar_leg = polynomial![(-1.0).powf(m as f32)];
-        let scalar_lag = polynomial![(-1.0).powf(p as f32)];
-
-        Self {
-            coeffs,
-            leg_poly: scalar_leg*derive(m, legendre(l, tol).unwrap()),
-            lag_poly: scalar_lag*derive(p, laguerre(p+q, tol).unwrap()),
+    pub fn builder(qn: QuantumNumbers) -> PsiBuilder {
+        PsiBuilder {qn, basis: Basis::default(), charge: 1, mu: 1.0}
+    }
+
+    /// Length scale a = a₀·(mₑ/μ)/Z in Bohr radii
+    pub fn length_scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Reduced mass μ in units of the electron mass
+    pub fn reduced_mass(&self) -> f32 {
+        self.mu
+    }
+
+    /// Energy level E_n = -μZ²/2n² in Hartree
+    pub fn energy(&self) -> f32 {
+        self.energy
+    }
+
+    /// Radius beyond which the density is negligible, ⟨r⟩ + 4σ
+    pub fn extent(&self) -> f32 {
+        let QuantumNumbers {n, l, ..} = self.qn;
+        let (n, l) = (n as f32, l as f32);
+        let r_1 = 0.5*(3.0*n*n-l*(l+1.0));
+        let r_2 = 0.5*n*n*(5.0*n*n+1.0-3.0*l*(l+1.0));
+        (r_1+4.0*(r_2-r_1*r_1).sqrt())*self.scale
+    }
+
+    pub fn quantum_numbers(&self) -> QuantumNumbers {
+        self.qn
+    }
+
+    pub fn basis(&self) -> Basis {
+        self.basis
+    }
+
+    pub fn symmetry(&self) -> Symmetry {
+        Symmetry::of(std::iter::once(self))
+    }
+
+    fn radial_f64(&self, r: f64) -> f64 {
+        let [c, u, _] = self.coeffs_f64;
+        let rho = r*c;
+        self.lag.eval(rho).0*u*rho.powi(self.qn.l as i32)*(-rho/2.0).exp()
+    }
+
+    /// Radial part R_nl(r), evaluated in double precision
+    pub fn radial(&self, r: f32) -> f32 {
+        self.radial_f64(r as f64) as f32
+    }
+
+    /// Radial probability density P(r) = r²·R_nl(r)²
+    pub fn radial_density(&self, r: f32) -> f32 {
+        let r = r as f64;
+        (r*r*self.radial_f64(r).powi(2)) as f32
+    }
+
+    /// Polar part of Y_lm, as a function of cos(θ), evaluated in double precision
+    pub fn polar(&self, cos_theta: f32) -> f32 {
+        let [_, _, o] = self.coeffs_f64;
+        let c = (cos_theta as f64).clamp(-1.0, 1.0);
+        let m_abs = self.qn.m.unsigned_abs() as i32;
+        (self.leg.eval(c).0*(1.0-c*c).sqrt().powi(m_abs)*o) as f32
+    }
+
+    /// Probability density of cos(θ), i.e. |Y_lm|² integrated over φ
+    pub fn angular_density(&self, cos_theta: f32) -> f32 {
+        // cos²(mφ) and sin²(mφ) of real orbitals average to 1/2
+        let real = if self.basis == Basis::Real && self.qn.m != 0 {0.5} else {1.0};
+        2.0*std::f32::consts::PI*real*self.polar(cos_theta).powi(2)
+    }
+
+    /// Radii of the n-l-1 radial nodes in increasing order, from the roots of L(ρ)
+    pub fn radial_nodes(&self) -> Vec<f32> {
+        let [c, ..] = self.coeffs_f64;
+        self.lag.roots().iter().map(|rho| (rho/c) as f32).collect()
+    }
+
+    /// Polar angles θ of the l-|m| nodal cones in increasing order, from the
+    /// roots of P(cos θ). A cone at θ = π/2 is the xy plane.
+    pub fn nodal_cones(&self) -> Vec<f32> {
+        self.leg.roots().iter().rev().map(|c| c.clamp(-1.0, 1.0).acos() as f32).collect()
+    }
+
+    /// Azimuthal angles φ in [0, π) of the |m| nodal planes through the z axis,
+    /// where cos(mφ) or sin(|m|φ) of real orbitals vanishes. |ψ|² of complex
+    /// orbitals is independent of φ and only vanishes on the z axis for m ≠ 0.
+    pub fn nodal_planes(&self) -> Vec<f32> {
+        let m_abs = self.qn.m.unsigned_abs();
+        let offset = match self.mode() {
+            2 => 0.5,
+            3 => 0.0,
+            _ => return Vec::new(),
+        };
+        (0..m_abs)
+            .map(|k| (k as f64+offset)*PI/m_abs as f64)
+            .map(|phi| phi as f32)
+            .collect()
+    }
+
+    pub fn nodes(&self) -> Nodes {
+        Nodes {
+            radial: self.radial_nodes(),
+            cones: self.nodal_cones(),
+            planes: self.nodal_planes(),
+        }
+    }
+
+    /// ⟨r^k⟩ by Simpson's rule over P(r), substituting r = s² to resolve
+    /// the inner lobes of states with many radial nodes
+    fn radial_moment(&self, k: i32) -> f64 {
+        let h = (3.0*self.extent() as f64).sqrt()/MOMENT_STEPS as f64;
+        let sum = (0..=MOMENT_STEPS)
+            .map(|i| {
+                let w = match i {
+                    0 | MOMENT_STEPS => 1.0,
+                    _ if i%2 == 1 => 4.0,
+                    _ => 2.0,
+                };
+                let s = i as f64*h;
+                let r = s*s;
+                w*2.0*s*r.powi(k+2)*self.radial_f64(r).powi(2)
+            })
+            .sum::<f64>();
+        sum*h/3.0
+    }
+
+    /// ⟨r⟩, which is (3n²-l(l+1))/2 times the length scale
+    pub fn mean_radius(&self) -> f32 {
+        self.radial_moment(1) as f32
+    }
+
+    /// ⟨r²⟩
+    pub fn mean_square_radius(&self) -> f32 {
+        self.radial_moment(2) as f32
+    }
+
+    /// ⟨1/r⟩, which is 1/n² over the length scale
+    pub fn mean_inverse_radius(&self) -> f32 {
+        self.radial_moment(-1) as f32
+    }
+
+    fn mode(&self) -> u32 {
+        match self.basis {
+            Basis::Complex if self.qn.m >= 0 => 0,
+            Basis::Complex => 1,
+            Basis::Real if self.qn.m >= 0 => 2,
+            Basis::Real => 3,
+        }
+    }
+
+    fn norm(&self) -> f32 {
+        self.coeffs[1].re*self.coeffs[3].re
+    }
+
+    pub fn factors(&self) -> Factors {
+        let QuantumNumbers {l, m, ..} = self.qn;
+        Factors {
+            c: self.coeffs[0].re,
+            norm: self.norm(),
+            l_m: l-m.unsigned_abs(),
+            m_abs: m.unsigned_abs(),
+            mode: self.mode(),
+            lag: self.lag_f32.steps().to_vec(),
+            leg: self.leg_f32.steps().to_vec(),
+        }
+    }
+
+    /// Spectroscopic label, e.g. 2p_x, 3d_z² or 4f_xyz for real orbitals
+    pub fn label(&self) -> String {
+        let QuantumNumbers {n, l, m} = self.qn;
+        let shell = match SUBSHELLS.get(l as usize) {
+            Some(c) => (*c as char).to_string(),
+            None => format!("(l={})", l),
+        };
+        let suffix = match (self.basis, l, m) {
+            (_, 0, _) => "",
+            (_, 1, 0) => "_z",
+            (_, 2, 0) => "_z²",
+            (_, 3, 0) => "_z³",
+            (Basis::Real, 1, 1) => "_x",
+            (Basis::Real, 1, -1) => "_y",
+            (Basis::Real, 2, 1) => "_xz",
+            (Basis::Real, 2, -1) => "_yz",
+            (Basis::Real, 2, 2) => "_x²-y²",
+            (Basis::Real, 2, -2) => "_xy",
+            (Basis::Real, 3, 1) => "_xz²",
+            (Basis::Real, 3, -1) => "_yz²",
+            (Basis::Real, 3, 2) => "_z(x²-y²)",
+            (Basis::Real, 3, -2) => "_xyz",
+            (Basis::Real, 3, 3) => "_x(x²-3y²)",
+            (Basis::Real, 3, -3) => "_y(3x²-y²)",
+            _ => return format!("{}{}(m={})", n, shell, m),
+        };
+        format!("{}{}{}", n, shell, suffix)
+    }
+
+    pub fn eval<const D: usize> (
+        &self,
+        x: &SVector<f32, D>,
+        y: &SVector<f32, D>,
+        z: &SVector<f32, D>,
+    ) -> SVector<Cf32, D> {
+        let mut out = SVector::<Cf32, D>::zeros();
+        self.eval_into(x.as_slice(), y.as_slice(), z.as_slice(), out.as_mut_slice());
+        out
+    }
+
+    /// Evaluates the factorized form at any number of points, with the square
+    /// root, exponential and polynomials computed by the SIMD kernels on
+    /// per-thread scratch buffers. The exponential is split into l+1 factors
+    /// s = e^{-ρ/(2l+2)}, one for L(ρ) and one for each power of ρ and c·w,
+    /// which keeps every factor within single precision up to high n.
+    /// Panics unless x, y, z and out have the same length.
+    pub fn eval_into(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        z: &[f32],
+        out: &mut [Cf32],
+    ) {
+        let n = out.len();
+        assert_eq!([x.len(), y.len(), z.len()], [n; 3], "err: x, y, z and out differ in length");
+        let QuantumNumbers {l, m, ..} = self.qn;
+        let (c, norm, mode) = (self.coeffs[0].re, self.norm(), self.mode());
+        let (l_m, m_abs) = (l-m.unsigned_abs(), m.unsigned_abs());
+        let k = -0.5/(l+1) as f32;
+        SCRATCH.with(|scratch| {
+            let Scratch {rho, lag, exp, leg} = &mut *scratch.borrow_mut();
+            for v in [&mut *rho, &mut *lag, &mut *exp, &mut *leg] {
+                v.resize(n, 0.0);
+            }
+            for i in 0..n {
+                rho[i] = x[i]*x[i]+y[i]*y[i]+z[i]*z[i];
+            }
+            simd::sqrt(rho);
+            for i in 0..n {
+                leg[i] = if rho[i] > 0.0 {z[i]/rho[i]} else {0.0};
+                rho[i] *= c;
+                lag[i] = rho[i];
+                exp[i] = k*rho[i];
+            }
+            simd::recurrence(self.lag_f32.steps(), lag);
+            simd::exp(exp);
+            simd::recurrence(self.leg_f32.steps(), leg);
+
+            for i in 0..n {
+                let s = exp[i];
+                let w = Cf32::new(x[i], if mode == 1 {-y[i]} else {y[i]})*(c*s);
+                let w_m = w.powu(m_abs);
+                let angular = match mode {
+                    2 => Cf32::from(w_m.re),
+                    3 => Cf32::from(w_m.im),
+                    _ => w_m,
+                };
+                out[i] = angular*(norm*lag[i]*s*leg[i]*(rho[i]*s).powi(l_m as i32));
+            }
+        });
+    }
+
+    /// Reference evaluation in double precision at a single point
+    pub fn eval_f64(&self, x: f64, y: f64, z: f64) -> Complex<f64> {
+        let QuantumNumbers {l, m, ..} = self.qn;
+        let [c, u, o] = self.coeffs_f64;
+        let r = (x*x+y*y+z*z).sqrt();
+        let rho = c*r;
+        let cos_theta = if r > 0.0 {z/r} else {0.0};
+        let w = Complex::new(x, if self.mode() == 1 {-y} else {y})*c;
+        let w_m = w.powu(m.unsigned_abs());
+        let angular = match self.mode() {
+            2 => Complex::from(w_m.re),
+            3 => Complex::from(w_m.im),
+            _ => w_m,
+        };
+        angular*u*o*self.lag.eval(rho).0*(-rho/2.0).exp()
+            *rho.powi((l-m.unsigned_abs()) as i32)*self.leg.eval(cos_theta).0
+    }
+
+    /// ψ and ∇ψ = (∂ψ/∂x, ∂ψ/∂y, ∂ψ/∂z) by forward-mode autodifferentiation
+    pub fn eval_grad<const D: usize> (
+        &self,
+        x: &SVector<f32, D>,
+        y: &SVector<f32, D>,
+        z: &SVector<f32, D>,
+    ) -> (SVector<Cf32, D>, [SVector<Cf32, D>; 3]) {
+        let psi = SVector::<Dual, D>::from_fn(|i, _| unsafe {
+            self.eval_dual(
+                Dual::var(*x.vget_unchecked(i), 0),
+                Dual::var(*y.vget_unchecked(i), 1),
+                Dual::var(*z.vget_unchecked(i), 2),
+            )
+        });
+
+        (psi.map(|v| v.re), [0, 1, 2].map(|j| psi.map(|v| v.eps[j])))
+    }
+
+    fn eval_dual(&self, x: Dual, y: Dual, z: Dual) -> Dual {
+        let r_2 = x*x+y*y;
+        let r_3 = (r_2+z*z).sqrt();
+        let rho = r_3*self.coeffs[0];
+        let (lag, lag_d) = self.lag_f32.eval(rho.re.re);
+        let r_nl = rho.chain(lag.into(), lag_d.into())*self.coeffs[1]
+                    *rho.powf(self.coeffs[4].re)
+                    *(rho*Cf32::from(-0.5)).exp();
+        let cos_theta = z/r_3;
+        let (leg, leg_d) = self.leg_f32.eval(cos_theta.re.re);
+        let theta_lm = cos_theta.chain(leg.into(), leg_d.into())
+                    *(-(cos_theta*cos_theta)+Cf32::from(1.0)).powf(self.coeffs[2].re);
+        let ei_mphi = ((x+y*Cf32::i())/r_2.sqrt()).powf(self.coeffs[5].re);
+        let ei_mphi = match self.basis {
+            Basis::Complex => ei_mphi,
+            Basis::Real if self.qn.m >= 0 => ei_mphi.re_part(),
+            Basis::Real => ei_mphi.im_part(),
+        };
+
+        ei_mphi*theta_lm*self.coeffs[3]*r_nl
+    }
+}
+
+/// Weighted sum of stationary states evolving as ψ(x, t) = Σ cₖ ψₖ(x) e^{-iEₖt},
+/// in atomic units (ħ = 1)
+pub struct Superposition {
+    terms: Vec<(Cf32, Psi)>,
+}
+
+impl Superposition {
+    /// Amplitudes are normalized, assuming the states are mutually orthogonal
+    pub fn new(terms: Vec<(Cf32, Psi)>) -> Result<Self, String> {
+        let norm = terms.iter().map(|(c, _)| c.norm_sqr()).sum::<f32>().sqrt();
+        if !(norm.is_finite() && norm > 0.0) {
+            return Err("err: amplitudes must be finite and not all zero".into());
         }
+        let terms = terms.into_iter().map(|(c, psi)| (c/norm, psi)).collect();
+
+        Ok(Self {terms})
+    }
+
+    /// Whether |ψ|² is constant in time, i.e. all terms are degenerate
+    pub fn is_stationary(&self) -> bool {
+        let e_0 = self.terms.first().map_or(0.0, |(_, psi)| psi.energy());
+        self.terms.iter().all(|(_, psi)| (psi.energy()-e_0).abs() < 1e-6)
+    }
+
+    /// Whether ψ is a single state with a real spatial part, so that its
+    /// sign is defined up to the global phase
+    pub fn is_real(&self) -> bool {
+        matches!(&self.terms[..], [(_, psi)] if psi.basis == Basis::Real || psi.qn.m == 0)
+    }
+
+    /// e^{-i·arg c} of the amplitude of a real state, which makes ψ real at t = 0
+    pub fn real_phase(&self) -> Option<Cf32> {
+        match &self.terms[..] {
+            [(c, _)] if self.is_real() => Some(c.conj()/c.norm()),
+            _ => None,
+        }
+    }
+
+    pub fn terms(&self) -> &[(Cf32, Psi)] {
+        &self.terms
+    }
+
+    /// Symmetry of |ψ|² shared by all terms, which is preserved under time evolution
+    pub fn symmetry(&self) -> Symmetry {
+        Symmetry::of(self.terms.iter().map(|(_, psi)| psi))
+    }
+
+    pub fn label(&self) -> String {
+        self.terms.iter()
+            .map(|(_, psi)| psi.label())
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+
+    pub fn length_scale(&self) -> f32 {
+        self.terms.iter().map(|(_, psi)| psi.length_scale()).fold(0.0, f32::max)
+    }
+
+    pub fn extent(&self) -> f32 {
+        self.terms.iter().map(|(_, psi)| psi.extent()).fold(0.0, f32::max)
+    }
+
+    /// Reduced mass of the first term, all terms are assumed to describe the same system
+    pub fn reduced_mass(&self) -> f32 {
+        self.terms.first().map_or(1.0, |(_, psi)| psi.reduced_mass())
     }
 
     pub fn eval<const D: usize> (
@@ -57,25 +610,390 @@ impl Psi {
         x: &SVector<f32, D>,
         y: &SVector<f32, D>,
         z: &SVector<f32, D>,
+        t: f32,
     ) -> SVector<Cf32, D> {
-        let r_2 = x.component_mul(x)+y.component_mul(y);
-        let r_3 = (r_2+z.component_mul(z)).map(|i| i.sqrt());
-        let rho = r_3.map(|i| i.abs()*self.coeffs[0].re);
-        let r_nl = rho.map(|i| self.lag_poly.evaluate(i)*self.coeffs[1].re)
-                    .component_mul(&rho.map(|i| i.powf(self.coeffs[4].re)))
-                    .component_mul(&rho.map(|i| (-i/2.0).exp()));
-        let cos_theta = z.component_div(&r_3)
-                    .map(|i| if i.abs() >= 1.0 {i.signum()*1.0} else {i})
-                    .map(|i| self.leg_poly.evaluate(i)*(1.0-i*i).powf(self.coeffs[2].re));
-        unsafe {
-            let ei_mtheta = SVector::<Cf32, D>::from_fn(|i, _| Cf32::from(y.vget_unchecked(i))*Cf32::i())
-                        .map_with_location(|i, _, v|
-                            (v+Cf32::from(x.vget_unchecked(i)))/Cf32::from(r_2.vget_unchecked(i)).sqrt())
-                        .map(|i| i.powf(self.coeffs[5].re));
-            let y_lm = ei_mtheta.map_with_location(|i, _, v|
-                        v*Cf32::from(cos_theta.vget_unchecked(i))*self.coeffs[3]);
-
-            y_lm.map_with_location(|i, _, v| v*Cf32::from(r_nl.vget_unchecked(i)))
+        let mut out = SVector::<Cf32, D>::zeros();
+        self.eval_into(x.as_slice(), y.as_slice(), z.as_slice(), t, out.as_mut_slice());
+        out
+    }
+
+    /// Sum of the terms at time t, for any number of points
+    pub fn eval_into(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        z: &[f32],
+        t: f32,
+        out: &mut [Cf32],
+    ) {
+        let mut term = TERM.take();
+        term.resize(out.len(), Cf32::default());
+        out.fill(Cf32::default());
+        for (c, psi) in &self.terms {
+            let phase = c*Cf32::new(0.0, -psi.energy()*t).exp();
+            psi.eval_into(x, y, z, &mut term);
+            for (o, v) in out.iter_mut().zip(&term) {
+                *o += v*phase;
+            }
+        }
+        TERM.set(term);
+    }
+
+    /// arg ψ in (-π, π] at time t. Real states are taken at t = 0 without the
+    /// phase of their amplitude, where the phase is 0 or π by the sign.
+    pub fn phase_into(
+        &self,
+        x: &[f32],
+        y: &[f32],
+        z: &[f32],
+        t: f32,
+        out: &mut [f32],
+    ) {
+        let mut psi = vec![Cf32::default(); out.len()];
+        let real = self.real_phase();
+        self.eval_into(x, y, z, if real.is_some() {0.0} else {t}, &mut psi);
+        let unit = real.unwrap_or(Cf32::from(1.0));
+        for (o, v) in out.iter_mut().zip(&psi) {
+            *o = (v*unit).arg();
+        }
+    }
+
+    pub fn eval_grad<const D: usize> (
+        &self,
+        x: &SVector<f32, D>,
+        y: &SVector<f32, D>,
+        z: &SVector<f32, D>,
+        t: f32,
+    ) -> (SVector<Cf32, D>, [SVector<Cf32, D>; 3]) {
+        let init = (SVector::zeros(), [(); 3].map(|_| SVector::zeros()));
+        self.terms.iter().fold(init, |(acc, acc_d), (c, psi)| {
+            let phase = c*Cf32::new(0.0, -psi.energy()*t).exp();
+            let (v, d) = psi.eval_grad(x, y, z);
+            let [d_x, d_y, d_z] = d.map(|d| d*phase);
+            let [a_x, a_y, a_z] = acc_d;
+            (acc+v*phase, [a_x+d_x, a_y+d_y, a_z+d_z])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::SVector;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::SmallRng;
+
+    const N_MAX: u32 = 10;
+
+    fn states(n_max: u32) -> impl Iterator<Item = QuantumNumbers> {
+        (1..=n_max).flat_map(|n| (0..n).flat_map(move |l| {
+            (-(l as i32)..=l as i32).map(move |m| QuantumNumbers::new(n, l, m).unwrap())
+        }))
+    }
+
+    /// Gauss-Legendre nodes and weights on [-1, 1], from the roots of P_k
+    fn gauss_legendre(k: u32) -> Vec<(f64, f64)> {
+        let p = Recurrence::legendre(k, 0);
+        p.roots().into_iter()
+            .map(|x| {
+                // The normalized polynomial is √(2k+1)·P_k
+                let d = p.eval(x).1/((2*k+1) as f64).sqrt();
+                (x, 2.0/((1.0-x*x)*d*d))
+            })
+            .collect()
+    }
+
+    /// Product quadrature of Simpson's rule in s = √r, Gauss-Legendre in cos(θ)
+    /// and the trapezoidal rule in φ, exact for |m| below azimuthal/2
+    fn sphere(r_max: f64, radial: usize, polar: u32, azimuthal: usize) -> Vec<([f64; 3], f64)> {
+        let h = r_max.sqrt()/radial as f64;
+        let angular = gauss_legendre(polar);
+        let mut grid = Vec::new();
+        for i in 1..=radial {
+            let w_r = if i == radial {1.0} else if i%2 == 1 {4.0} else {2.0};
+            let s = i as f64*h;
+            let r = s*s;
+            let w_r = w_r*h/3.0*2.0*s*r*r;
+            for &(c, w_c) in &angular {
+                let sin = (1.0-c*c).sqrt();
+                for j in 0..azimuthal {
+                    let phi = 2.0*PI*(j as f64+0.5)/azimuthal as f64;
+                    let w = w_r*w_c*2.0*PI/azimuthal as f64;
+                    grid.push(([r*sin*phi.cos(), r*sin*phi.sin(), r*c], w));
+                }
+            }
+        }
+        grid
+    }
+
+    fn gram(states: &[Psi], grid: &[([f64; 3], f64)]) -> Vec<Vec<Complex<f64>>> {
+        let vals: Vec<Vec<_>> = states.iter()
+            .map(|psi| grid.iter().map(|([x, y, z], _)| psi.eval_f64(*x, *y, *z)).collect())
+            .collect();
+        vals.iter()
+            .map(|a| vals.iter()
+                .map(|b| a.iter().zip(b).zip(grid)
+                    .map(|((a, b), (_, w))| a.conj()*b*w)
+                    .sum())
+                .collect())
+            .collect()
+    }
+
+    fn assert_orthonormal(states: &[Psi], grid: &[([f64; 3], f64)]) {
+        let g = gram(states, grid);
+        for (i, row) in g.iter().enumerate() {
+            for (j, v) in row.iter().enumerate() {
+                let expected = if i == j {1.0} else {0.0};
+                assert!(
+                    (v-expected).norm() < 1e-5,
+                    "⟨{}|{}⟩ = {}", states[i].label(), states[j].label(), v,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn labels() {
+        let label = |n, l, m, basis| {
+            Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build().label()
+        };
+        assert_eq!(label(2, 1, 1, Basis::Real), "2p_x");
+        assert_eq!(label(3, 2, 0, Basis::Complex), "3d_z²");
+        assert_eq!(label(5, 4, -2, Basis::Complex), "5g(m=-2)");
+        assert_eq!(label(22, 21, 0, Basis::Complex), "22(l=21)(m=0)");
+    }
+
+    /// ψ at spherical coordinates
+    fn eval_spherical(psi: &Psi, r: f64, theta: f64, phi: f64) -> Complex<f64> {
+        let (s, c) = theta.sin_cos();
+        psi.eval_f64(r*s*phi.cos(), r*s*phi.sin(), r*c)
+    }
+
+    /// ψ vanishes and changes sign at every radial node, nodal cone and nodal
+    /// plane, relative to the largest |ψ| along the line through the node
+    #[test]
+    fn nodes() {
+        let (theta, phi) = (1.1, 0.7);
+        let assert_node = |psi: &Psi, at: &dyn Fn(f64) -> Complex<f64>, x: f64, range: f64, kind: &str| {
+            let scale = (0..=256).map(|i| at(range*i as f64/256.0).norm()).fold(0.0, f64::max);
+            let delta = 1e-3*range;
+            let (v, a, b) = (at(x), at(x-delta), at(x+delta));
+            let label = psi.label();
+            assert!(v.norm() < 1e-4*scale, "{} {} at {}: {} of {}", label, kind, x, v, scale);
+            assert!((a*b.conj()).re < 0.0, "{} {} at {}: {} and {}", label, kind, x, a, b);
+        };
+        for basis in [Basis::Complex, Basis::Real] {
+            for qn in states(8) {
+                let psi = Psi::builder(qn).basis(basis).build();
+                let Nodes {radial, cones, planes} = psi.nodes();
+                let (l, m_abs) = (qn.l() as usize, qn.m().unsigned_abs() as usize);
+                assert_eq!(radial.len(), qn.n() as usize-l-1);
+                assert_eq!(cones.len(), l-m_abs);
+                assert_eq!(planes.len(), if basis == Basis::Real {m_abs} else {0});
+
+                let extent = psi.extent() as f64;
+                // Radius of the largest |ψ| off the nodes, for the angular nodes
+                let r = (1..64)
+                    .map(|i| extent*i as f64/64.0)
+                    .max_by(|a, b| psi.radial_f64(*a).abs().total_cmp(&psi.radial_f64(*b).abs()))
+                    .unwrap();
+                for &x in &radial {
+                    assert_node(&psi, &|x| eval_spherical(&psi, x, theta, phi), x as f64, extent, "radial node");
+                }
+                for &x in &cones {
+                    assert_node(&psi, &|x| eval_spherical(&psi, r, x, phi), x as f64, PI, "cone");
+                }
+                for &x in &planes {
+                    assert_node(&psi, &|x| eval_spherical(&psi, r, theta, x), x as f64, 2.0*PI, "plane");
+                }
+            }
+        }
+
+        let nodes = |n, l, m, basis| {
+            Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build().nodes()
+        };
+        let close = |a: &[f32], b: &[f32]| a.len() == b.len() && a.iter().zip(b).all(|(a, b)| (a-b).abs() < 1e-5);
+        let two_s = nodes(2, 0, 0, Basis::Complex);
+        assert!(close(&two_s.radial, &[2.0]), "{:?}", two_s.radial);
+        let three_p = nodes(3, 1, 0, Basis::Complex);
+        assert!(close(&three_p.radial, &[6.0]), "{:?}", three_p.radial);
+        assert!(close(&three_p.cones, &[std::f32::consts::FRAC_PI_2]), "{:?}", three_p.cones);
+        let three_d = nodes(3, 2, -2, Basis::Real);
+        assert!(close(&three_d.planes, &[0.0, std::f32::consts::FRAC_PI_2]), "{:?}", three_d.planes);
+        assert!(three_d.radial.is_empty() && three_d.cones.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "differ in length")]
+    fn eval_into_lengths() {
+        let psi = Psi::builder(QuantumNumbers::new(2, 1, 0).unwrap()).build();
+        psi.eval_into(&[1.0; 4], &[1.0; 4], &[1.0; 3], &mut [Cf32::default(); 4]);
+    }
+
+    /// Condon-Shortley phase, Y_1^1 = -√(3/8π)·sinθ·e^{iφ} is negative on +x
+    /// while Y_1^-1 and the real p_x orbital are positive
+    #[test]
+    fn condon_shortley() {
+        let psi = |m, basis| Psi::builder(QuantumNumbers::new(2, 1, m).unwrap()).basis(basis).build();
+        assert!(psi(1, Basis::Complex).eval_f64(1.0, 0.0, 0.0).re < 0.0);
+        assert!(psi(-1, Basis::Complex).eval_f64(1.0, 0.0, 0.0).re > 0.0);
+        assert!(psi(1, Basis::Real).eval_f64(1.0, 0.0, 0.0).re > 0.0);
+        let x = [1.0, 0.5].into();
+        let zero = [0.0, 0.0].into();
+        assert!(psi(1, Basis::Complex).eval::<2>(&x, &zero, &zero).iter().all(|v| v.re < 0.0));
+    }
+
+    #[test]
+    fn superposition_amplitudes() {
+        let psi = |n| Psi::builder(QuantumNumbers::new(n, 0, 0).unwrap()).build();
+        assert!(Superposition::new(Vec::new()).is_err());
+        assert!(Superposition::new(vec![(Cf32::from(0.0), psi(1)), (Cf32::from(0.0), psi(2))]).is_err());
+        assert!(Superposition::new(vec![(Cf32::from(f32::NAN), psi(1))]).is_err());
+        let sup = Superposition::new(vec![(Cf32::from(3.0), psi(1)), (Cf32::new(0.0, 4.0), psi(2))]).unwrap();
+        let c: Vec<_> = sup.terms().iter().map(|(c, _)| *c).collect();
+        assert_eq!(c, [Cf32::from(0.6), Cf32::new(0.0, 0.8)]);
+    }
+
+    /// Compares eval_grad with central differences of eval at points in a
+    /// sphere of a few Bohr radii, away from the z axis
+    fn assert_gradient<F, G>(eval: F, eval_grad: G, r: f32)
+    where
+        F: Fn(&SVector<f32, 8>, &SVector<f32, 8>, &SVector<f32, 8>) -> SVector<Cf32, 8>,
+        G: Fn(&SVector<f32, 8>, &SVector<f32, 8>, &SVector<f32, 8>) -> [SVector<Cf32, 8>; 3],
+    {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut p = [(); 3].map(|_| SVector::<f32, 8>::from_fn(|_, _| rng.gen_range(-r..r)));
+        p[0].iter_mut().for_each(|x| *x += x.signum()*0.1*r);
+        let grad = eval_grad(&p[0], &p[1], &p[2]);
+        let scale = grad.iter().flat_map(|d| d.iter()).map(|v| v.norm()).fold(0.0, f32::max);
+        let h = 1e-2*r;
+        for j in 0..3 {
+            let (mut a, mut b) = (p, p);
+            a[j].add_scalar_mut(h);
+            b[j].add_scalar_mut(-h);
+            let diff = (eval(&a[0], &a[1], &a[2])-eval(&b[0], &b[1], &b[2]))/Cf32::from(2.0*h);
+            for (d, f) in grad[j].iter().zip(diff.iter()) {
+                assert!((d-f).norm() < 2e-3*scale, "∂{}: {} vs {}", j, d, f);
+            }
+        }
+    }
+
+    #[test]
+    fn gradient() {
+        for basis in [Basis::Complex, Basis::Real] {
+            for (n, l, m) in [(1, 0, 0), (2, 1, -1), (3, 2, 1), (4, 3, -2), (5, 2, 2), (6, 5, 5)] {
+                let psi = Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build();
+                let r = 0.5*psi.extent();
+                assert_gradient(|x, y, z| psi.eval(x, y, z), |x, y, z| psi.eval_grad(x, y, z).1, r);
+            }
+        }
+        let terms = [(2, 1, 1, Basis::Complex), (3, 2, -1, Basis::Real), (3, 0, 0, Basis::Complex)]
+            .map(|(n, l, m, basis)| Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build());
+        let amplitudes = [Cf32::new(1.0, 0.0), Cf32::new(0.5, 0.5), Cf32::new(0.0, -0.7)];
+        let sup = Superposition::new(amplitudes.into_iter().zip(terms).collect()).unwrap();
+        let t = 1.3;
+        assert_gradient(|x, y, z| sup.eval(x, y, z, t), |x, y, z| sup.eval_grad(x, y, z, t).1, 0.5*sup.extent());
+    }
+
+    /// ⟨r⟩ = (3n²-l(l+1))/2, ⟨r²⟩ = n²(5n²+1-3l(l+1))/2 and ⟨1/r⟩ = 1/n² in units of a₀/Z
+    #[test]
+    fn radial_moments() {
+        for qn in states(N_MAX).filter(|qn| qn.m() == 0) {
+            let psi = Psi::builder(qn).build();
+            let (n, l) = (qn.n() as f32, qn.l() as f32);
+            let r_1 = 0.5*(3.0*n*n-l*(l+1.0));
+            let r_2 = 0.5*n*n*(5.0*n*n+1.0-3.0*l*(l+1.0));
+            assert!((psi.mean_radius()/r_1-1.0).abs() < 1e-5, "{}: ⟨r⟩ = {}", psi.label(), psi.mean_radius());
+            assert!((psi.mean_square_radius()/r_2-1.0).abs() < 1e-5, "{}: ⟨r²⟩ = {}", psi.label(), psi.mean_square_radius());
+            assert!((psi.mean_inverse_radius()*n*n-1.0).abs() < 1e-5, "{}: ⟨1/r⟩ = {}", psi.label(), psi.mean_inverse_radius());
+        }
+        // Scaled by the length scale a₀/(μZ)
+        let psi = Psi::builder(QuantumNumbers::new(3, 1, 0).unwrap()).charge(2).reduced_mass(1.0, 1.0).build();
+        assert!((psi.mean_radius()/(12.5*psi.length_scale())-1.0).abs() < 1e-5);
+    }
+
+    /// Real states have the phase 0 or π by the sign of ψ, for any amplitude and time
+    #[test]
+    fn real_phase() {
+        for (n, l, m, basis) in [(3, 2, -1, Basis::Real), (4, 2, 0, Basis::Complex)] {
+            let psi = Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build();
+            let reference = Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build();
+            let sup = Superposition::new(vec![(Cf32::new(-0.3, 0.8), psi)]).unwrap();
+            let mut rng = SmallRng::seed_from_u64(2);
+            let [x, y, z] = [(); 3].map(|_| (0..64).map(|_| rng.gen_range(-20.0..20.0)).collect::<Vec<f32>>());
+            let mut phase = vec![0.0; 64];
+            sup.phase_into(&x, &y, &z, 3.7, &mut phase);
+            for i in 0..64 {
+                let v = reference.eval_f64(x[i] as f64, y[i] as f64, z[i] as f64).re;
+                let expected = if v < 0.0 {std::f32::consts::PI} else {0.0};
+                assert!((phase[i].abs()-expected).abs() < 1e-4, "{} at {}: {} vs {}", sup.label(), i, phase[i], v);
+            }
+        }
+    }
+
+    #[test]
+    fn normalized_and_radially_orthogonal() {
+        // Angular parts are fixed by l and m, so the grid of each (l, m) integrates
+        // |Y_lm|² exactly and covers every n up to N_MAX
+        for basis in [Basis::Complex, Basis::Real] {
+            for qn in states(N_MAX).filter(|qn| qn.n() == N_MAX) {
+                let (l, m) = (qn.l(), qn.m());
+                let states: Vec<_> = (l+1..=N_MAX)
+                    .map(|n| Psi::builder(QuantumNumbers::new(n, l, m).unwrap()).basis(basis).build())
+                    .collect();
+                let r_max = 2.0*states.last().unwrap().extent() as f64;
+                let grid = sphere(r_max, 1024, l+1, 2*m.unsigned_abs() as usize+1);
+                assert_orthonormal(&states, &grid);
+            }
+        }
+    }
+
+    /// The single precision factorized form follows the double precision reference
+    /// for every state up to n = 20, at radii spanning the extent. Errors are relative
+    /// to the largest |ψ| at the same or a larger radius, at least 10⁻⁶ of the peak,
+    /// so that points close to a node are measured against the surrounding lobes.
+    #[test]
+    fn single_precision() {
+        const POINTS: usize = 64;
+        let mut rng = SmallRng::seed_from_u64(3);
+        let mut out = vec![Cf32::from(0.0); POINTS];
+        for basis in [Basis::Complex, Basis::Real] {
+            for qn in states(20) {
+                let psi = Psi::builder(qn).basis(basis).build();
+                let [mut x, mut y, mut z] = [(); 3].map(|_| vec![0.0f32; POINTS]);
+                for i in 0..POINTS {
+                    let r = psi.extent()*(i as f32+0.5)/POINTS as f32;
+                    let cos_theta: f32 = rng.gen_range(-1.0..1.0);
+                    let phi: f32 = rng.gen_range(0.0..2.0*std::f32::consts::PI);
+                    let sin_theta = (1.0-cos_theta*cos_theta).sqrt();
+                    (x[i], y[i], z[i]) = (r*sin_theta*phi.cos(), r*sin_theta*phi.sin(), r*cos_theta);
+                }
+                psi.eval_into(&x, &y, &z, &mut out);
+                let expected: Vec<_> = (0..POINTS)
+                    .map(|i| psi.eval_f64(x[i] as f64, y[i] as f64, z[i] as f64))
+                    .collect();
+                let max = expected.iter().map(|v| v.norm()).fold(0.0, f64::max);
+                let mut envelope = expected.iter().map(|v| v.norm().max(1e-6*max)).collect::<Vec<_>>();
+                for i in (0..POINTS-1).rev() {
+                    envelope[i] = envelope[i].max(envelope[i+1]);
+                }
+                for i in 0..POINTS {
+                    let v = Complex::new(out[i].re as f64, out[i].im as f64);
+                    let err = (v-expected[i]).norm()/envelope[i];
+                    assert!(err < 1e-4, "{} at {}: {} vs {}", psi.label(), i, v, expected[i]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn angularly_orthogonal() {
+        for basis in [Basis::Complex, Basis::Real] {
+            let states: Vec<_> = states(4)
+                .map(|qn| Psi::builder(qn).basis(basis).build())
+                .collect();
+            let r_max = 2.0*states.last().unwrap().extent() as f64;
+            assert_orthonormal(&states, &sphere(r_max, 512, 8, 16));
         }
     }
 }